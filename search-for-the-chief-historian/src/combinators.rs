@@ -0,0 +1,111 @@
+//! Stateful adapters for predicates, meant for `Iterator::filter`, `take_while` and friends.
+//!
+//! Every adapter keeps its own state, so a fresh one is needed for every pass over the data.
+
+/// Extension trait for predicates taking their argument by reference.
+///
+/// Closures need their argument type spelled out, e.g. `(|x: &u32| *x > 2).with_tolerance(1)`,
+/// for the compiler to see them as predicates over any lifetime.
+pub trait PredicateExt<T: ?Sized>: FnMut(&T) -> bool + Sized {
+    /// Forgive the first `tolerance` failures.
+    ///
+    /// Returns `true` whenever the predicate does, and for the first `tolerance` times it does not.
+    fn with_tolerance(mut self, mut tolerance: usize) -> impl FnMut(&T) -> bool {
+        move |x| {
+            if self(x) {
+                return true;
+            }
+            let tolerated = tolerance > 0;
+            tolerance = tolerance.saturating_sub(1);
+            tolerated
+        }
+    }
+
+    /// Returns `true` until the predicate first returns `expected`, and `false` from then on.
+    ///
+    /// The element matching `expected` is not let through. Unlike `|x| predicate(x) != expected`
+    /// the result latches, which matters for `filter`.
+    fn until_first(mut self, expected: bool) -> impl FnMut(&T) -> bool {
+        let mut found = false;
+        move |x| {
+            found = found || self(x) == expected;
+            !found
+        }
+    }
+
+    /// Returns `true` while the predicate does, including the first element for which it does not.
+    ///
+    /// `take_while` with this adapter yields the first failing element as well.
+    fn take_while_inclusive(mut self) -> impl FnMut(&T) -> bool {
+        let mut done = false;
+        move |x| {
+            if done {
+                return false;
+            }
+            done = !self(x);
+            true
+        }
+    }
+
+    /// Pass the predicate's verdicts through unchanged, counting the `false` ones into `failures`.
+    fn count_failures(mut self, failures: &mut usize) -> impl FnMut(&T) -> bool {
+        move |x| {
+            let verdict = self(x);
+            *failures += usize::from(!verdict);
+            verdict
+        }
+    }
+}
+
+impl<T: ?Sized, P: FnMut(&T) -> bool> PredicateExt<T> for P {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUMBERS: [u32; 8] = [1, 5, 2, 6, 7, 3, 8, 4];
+
+    fn small(x: &u32) -> bool {
+        *x < 5
+    }
+
+    #[test]
+    fn test_with_tolerance() {
+        assert_eq!(NUMBERS.iter().copied().filter(small.with_tolerance(2)).collect::<Vec<_>>(), [1, 5, 2, 6, 3, 4]);
+        assert_eq!(NUMBERS.iter().copied().filter(small.with_tolerance(0)).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert!(NUMBERS.iter().all(small.with_tolerance(4)));
+        assert!(!NUMBERS.iter().all(small.with_tolerance(3)));
+    }
+
+    #[test]
+    fn test_until_first() {
+        assert_eq!(NUMBERS.iter().copied().filter(small.until_first(false)).collect::<Vec<_>>(), [1]);
+        assert_eq!(NUMBERS.iter().copied().filter((|x: &u32| *x == 7).until_first(true)).collect::<Vec<_>>(), [1, 5, 2, 6]);
+        assert_eq!(NUMBERS.iter().copied().filter(small.until_first(true)).count(), 0);
+    }
+
+    #[test]
+    fn test_take_while_inclusive() {
+        assert_eq!(NUMBERS.iter().copied().take_while(small.take_while_inclusive()).collect::<Vec<_>>(), [1, 5]);
+        assert_eq!(NUMBERS.iter().copied().filter(small.take_while_inclusive()).collect::<Vec<_>>(), [1, 5]);
+        assert_eq!([1u32, 2].iter().copied().take_while(small.take_while_inclusive()).collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn test_count_failures() {
+        let mut failures = 0;
+        assert_eq!(NUMBERS.iter().copied().filter(small.count_failures(&mut failures)).count(), 4);
+        assert_eq!(failures, 4);
+    }
+
+    #[test]
+    fn test_composition() {
+        let mut failures = 0;
+        let kept = NUMBERS.iter()
+            .copied()
+            .filter(small.count_failures(&mut failures).with_tolerance(1))
+            .collect::<Vec<_>>();
+        assert_eq!(kept, [1, 5, 2, 3, 4]);
+        assert_eq!(failures, 4);
+    }
+}
@@ -1,28 +1,139 @@
-use std::{io::{self}, path::PathBuf};
+use std::{fs, io::{self}, path::{Path, PathBuf}, time::{Duration, SystemTime, UNIX_EPOCH}};
 use eyre::{eyre, Result};
 use ureq::{AgentBuilder, Cookie};
 
+use crate::{combinators::PredicateExt, puzzle::Puzzle};
+
 pub mod ext {
 
     pub trait Split {
+        /// Split into `N` parts of equal length, if possible.
         fn split_exact<const N: usize>(&self) -> Option<[&Self; N]>;
     }
 
     impl<T> Split for [T] {
         fn split_exact<const N: usize>(&self) -> Option<[&Self; N]> {
-            self.try_into().ok()
+            (N > 0 && self.len().is_multiple_of(N)).then(|| {
+                let size = self.len() / N;
+                std::array::from_fn(|index| &self[index * size..(index + 1) * size])
+            })
+        }
+    }
+}
+
+pub mod collections {
+    use std::ops::{Deref, DerefMut};
+
+    use arrayvec::ArrayVec;
+
+    /// Vector keeping up to `N` elements inline, spilling to the heap past that.
+    #[derive(Debug, Clone)]
+    pub enum SmallVec<T, const N: usize> {
+        Inline(ArrayVec<T, N>),
+        Heap(Vec<T>),
+    }
+
+    impl<T, const N: usize> SmallVec<T, N> {
+        pub const fn new() -> Self {
+            Self::Inline(ArrayVec::new_const())
+        }
+
+        pub fn push(&mut self, value: T) {
+            match self {
+                Self::Inline(inline) => if let Err(overflow) = inline.try_push(value) {
+                    let mut heap = Vec::with_capacity(2 * N + 1);
+                    heap.extend(inline.drain(..));
+                    heap.push(overflow.element());
+                    *self = Self::Heap(heap);
+                },
+                Self::Heap(heap) => heap.push(value),
+            }
+        }
+
+        pub fn is_spilled(&self) -> bool {
+            matches!(self, Self::Heap(_))
+        }
+    }
+
+    impl<T, const N: usize> Default for SmallVec<T, N> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T, const N: usize> Deref for SmallVec<T, N> {
+        type Target = [T];
+
+        fn deref(&self) -> &Self::Target {
+            match self {
+                Self::Inline(inline) => inline,
+                Self::Heap(heap) => heap,
+            }
+        }
+    }
+
+    impl<T, const N: usize> DerefMut for SmallVec<T, N> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            match self {
+                Self::Inline(inline) => inline,
+                Self::Heap(heap) => heap,
+            }
+        }
+    }
+
+    impl<T, const N: usize> Extend<T> for SmallVec<T, N> {
+        fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            iter.into_iter().for_each(|value| self.push(value));
+        }
+    }
+
+    impl<T, const N: usize> FromIterator<T> for SmallVec<T, N> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            let mut vec = Self::new();
+            vec.extend(iter);
+            vec
         }
     }
 }
 
-pub trait Solution<const DAY: usize> {
+pub mod bench {
+    use std::time::{Duration, Instant};
+
+    /// Average wall-clock time of `f` over `iterations` runs, after one warm-up run.
+    pub fn measure<T>(iterations: u32, mut f: impl FnMut() -> T) -> Duration {
+        std::hint::black_box(f());
+        let start = Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(f());
+        }
+        start.elapsed() / iterations.max(1)
+    }
+
+    /// Linear congruential generator, deterministic garbage for tests and benchmarks.
+    pub struct Lcg {
+        state: u64,
+    }
+
+    impl Lcg {
+        pub fn new(seed: u64) -> Self {
+            Self { state: seed ^ 0x2545_f491_4f6c_dd1d }
+        }
+
+        pub fn next_u32(&mut self) -> u32 {
+            self.state = self.state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            (self.state >> 33) as u32
+        }
+    }
+}
+
+pub trait Solution<const DAY: usize, const PART: usize> {
     type Result;
 
-    fn solve() -> Self::Result {
-        Self::try_solve().unwrap()
+    fn solve(input: &str) -> Self::Result {
+        Self::try_solve(input).unwrap()
     }
 
-    fn try_solve() -> eyre::Result<Self::Result>;
+    fn try_solve(input: &str) -> eyre::Result<Self::Result>;
 }
 
 macro_rules! iter {
@@ -31,31 +142,123 @@ macro_rules! iter {
     };
 }
 
-pub mod functional {
-    pub fn delay<T>(mut predicate: impl for<'a> FnMut(&'a T) -> bool, expected: bool) -> impl for<'a> FnMut(&'a T) -> bool {
-        let mut found = false;
-        move |x| {
-            if found {
-                return expected;
-            }
-            let result = predicate(x);
-            if result == expected {
-                // this must be the first time condition returns true
-                // we should yield i, and then no more
-                found = true;
-            }
-            !expected
+pub struct Config {
+    input_cache: PathBuf,
+    user_agent: String,
+    min_request_interval: Duration,
+    offline: bool,
+}
+
+impl Config {
+    const YEAR: usize = 2024;
+    const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(5);
+    const OFFLINE_SWITCH: &'static str = "--offline";
+
+    /// Default configuration with overrides taken from the command line and the environment.
+    ///
+    /// * `--offline` disables all network access, the cache is still used.
+    /// * `AOC_CONTACT` is appended to the `User-Agent` so that the puzzle server knows whom to reach.
+    /// * `AOC_USER_AGENT` replaces the `User-Agent` altogether.
+    pub fn from_env() -> Self {
+        let contact = std::env::var("AOC_CONTACT").ok();
+        Self {
+            input_cache: PathBuf::from("inputs"),
+            user_agent: std::env::var("AOC_USER_AGENT")
+                .unwrap_or_else(|_| Self::default_user_agent(contact.as_deref())),
+            min_request_interval: Self::MIN_REQUEST_INTERVAL,
+            offline: std::env::args().any(|arg| arg == Self::OFFLINE_SWITCH),
         }
     }
+
+    fn default_user_agent(contact: Option<&str>) -> String {
+        let agent = concat!(
+            env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),
+            " (+https://github.com/mikiSpoko200/advent-of-code",
+        );
+        match contact {
+            Some(contact) => format!("{agent}; {contact})"),
+            None => format!("{agent})"),
+        }
+    }
+
+    pub fn input_cache(mut self, input_cache: impl Into<PathBuf>) -> Self {
+        self.input_cache = input_cache.into();
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn min_request_interval(mut self, interval: Duration) -> Self {
+        self.min_request_interval = interval;
+        self
+    }
+
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
 }
 
-pub struct Config {
-    input_cache: PathBuf,
+/// Enforces a minimum interval between requests to the puzzle server.
+///
+/// The time of the last request is kept in a file so that the limit holds across runs.
+pub struct Throttle {
+    stamp: PathBuf,
+    interval: Duration,
+    /// Serializes waiting, so that tasks running in parallel do not all take the same slot.
+    lock: std::sync::Mutex<()>,
+}
+
+impl Throttle {
+    const STAMP_FILE: &'static str = ".last-request";
+
+    pub fn new(cache: &Path, interval: Duration) -> Self {
+        Self {
+            stamp: cache.join(Self::STAMP_FILE),
+            interval,
+            lock: std::sync::Mutex::default(),
+        }
+    }
+
+    fn last_request(&self) -> Option<SystemTime> {
+        let millis = fs::read_to_string(&self.stamp).ok()?.trim().parse().ok()?;
+        Some(UNIX_EPOCH + Duration::from_millis(millis))
+    }
+
+    /// Time left until the next request is allowed.
+    pub fn remaining(&self) -> Duration {
+        self.last_request()
+            .and_then(|last| last.elapsed().ok())
+            .map_or(Duration::ZERO, |elapsed| self.interval.saturating_sub(elapsed))
+    }
+
+    /// Block until the next request is allowed and record it as made.
+    pub fn wait(&self) -> io::Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::thread::sleep(self.remaining());
+        self.record()
+    }
+
+    fn record(&self) -> io::Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(io::Error::other)?;
+        if let Some(parent) = self.stamp.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.stamp, now.as_millis().to_string())
+    }
 }
 
 pub struct InputManager {
     agent: ureq::Agent,
+    throttle: Throttle,
     config: Config,
+    /// Held for a whole fetch, so that tasks missing the cache together download only once.
+    fetching: std::sync::Mutex<()>,
 }
 
 #[derive(Debug)]
@@ -75,7 +278,7 @@ impl<T> From<LoadingReport<T>> for Result<T, eyre::Report> {
 impl<V> FromIterator<io::Result<V>> for LoadingReport<V> {
     fn from_iter<T: IntoIterator<Item = io::Result<V>>>(iter: T) -> Self {
         iter.into_iter()
-            .take_while(functional::delay(Result::is_err, true))
+            .take_while(Result::is_err.take_while_inclusive())
             .fold(Self { report: eyre!("encountered multiple errors"), data: None }, |mut report, err| {
                 match err {
                     Ok(data) => { report.data = Some(data); },
@@ -86,47 +289,223 @@ impl<V> FromIterator<io::Result<V>> for LoadingReport<V> {
     }
 }
 
-type Loader = fn(&InputManager) -> io::Result<String>;
+/// Something the puzzle server provides for a given day.
+#[derive(Debug, Clone, Copy)]
+pub enum Resource {
+    /// Input for one part of a day, the server has a single input shared by both parts.
+    Input { day: usize, part: usize },
+    Puzzle(usize),
+}
 
-impl InputManager {
-    const AUTH_COOKIE: usize = include_str!("../secret/cookie.txt");
+impl Resource {
+    fn path(&self) -> String {
+        match self {
+            Resource::Input { day, .. } => format!("/day/{day}/input"),
+            Resource::Puzzle(day) => format!("/day/{day}"),
+        }
+    }
+
+    /// Names the resource may be cached under, most specific first.
+    ///
+    /// Inputs can be overridden per part with `input-{day}-{part}.txt`, fetched ones are stored
+    /// under the last name, shared by both parts.
+    fn file_names(&self) -> Vec<String> {
+        match self {
+            Resource::Input { day, part } => vec![format!("input-{day}-{part}.txt"), format!("input-{day}.txt")],
+            Resource::Puzzle(day) => vec![format!("puzzle-{day}.html")],
+        }
+    }
+}
 
-    fn create_agent() -> ureq::Agent {
-        let cookies = Cookie::new("session", Self::AUTH_COOKIE);
+type Loader = fn(&InputManager, Resource) -> io::Result<String>;
 
+impl Default for InputManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputManager {
+    const BASE_URL: &'static str = "https://adventofcode.com";
+    const AUTH_COOKIE: &'static str = "secret/cookie.txt";
+
+    fn create_agent(config: &Config) -> ureq::Agent {
         AgentBuilder::new()
-            .cookie_store()
+            .user_agent(&config.user_agent)
             .build()
     }
 
     pub fn new() -> Self {
+        Self::with_config(Config::from_env())
+    }
 
+    pub fn with_config(config: Config) -> Self {
         Self {
-            agent: todo!(),
-            config: todo!(),
+            agent: Self::create_agent(&config),
+            throttle: Throttle::new(&config.input_cache, config.min_request_interval),
+            config,
+            fetching: std::sync::Mutex::default(),
         }
     }
 
+    pub fn input(&self, day: usize, part: usize) -> eyre::Result<String> {
+        self.load(Resource::Input { day, part })
+    }
+
+    /// Puzzle description for `day`, the page is cached next to the input.
+    pub fn puzzle(&self, day: usize) -> eyre::Result<Puzzle> {
+        self.load(Resource::Puzzle(day))
+            .map(|html| Puzzle::from_html(&html))
+    }
+
     // TODO: chain of resposibility + error accumulation
-    pub fn input(&self) -> eyre::Result<String> {
+    pub fn load(&self, resource: Resource) -> eyre::Result<String> {
         Self::chain_of_command()
-            .map(|loader| loader(self))
+            .map(|loader| loader(self, resource))
             .collect::<LoadingReport<String>>()
             .into()
     }
 
-    fn fetch(&self) -> io::Result<String> {
-        self.agent.get(path)
+    fn cached(&self, resource: Resource) -> PathBuf {
+        let names = resource.file_names();
+        self.config.input_cache.join(names.last().expect("every resource has a file name"))
+    }
+
+    fn session(&self) -> io::Result<String> {
+        std::env::var("AOC_SESSION")
+            .or_else(|_| fs::read_to_string(Self::AUTH_COOKIE))
+            .map(|session| session.trim().to_owned())
+    }
+
+    fn get(&self, resource: Resource) -> io::Result<String> {
+        let path = resource.path();
+        if self.config.offline {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("refusing to fetch {path}: running in offline mode ({})", Config::OFFLINE_SWITCH),
+            ));
+        }
+        // puzzle pages are public, logged in they also show the second part once the first is solved
+        let session = match resource {
+            Resource::Input { .. } => Some(self.session()?),
+            Resource::Puzzle(_) => self.session().ok(),
+        };
+        self.throttle.wait()?;
+        let request = self.agent.get(&format!("{}/{}{path}", Self::BASE_URL, Config::YEAR));
+        let request = match session {
+            Some(session) => request.set("Cookie", &Cookie::new("session", session).to_string()),
+            None => request,
+        };
+        request
+            .call()
+            .map_err(io::Error::other)?
+            .into_string()
+    }
+
+    fn fetch(&self, resource: Resource) -> io::Result<String> {
+        let _guard = self.fetching.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // another task may have fetched the resource while this one waited for its turn
+        if let Ok(data) = self.read_form_disc(resource) {
+            return Ok(data);
+        }
+        let data = self.get(resource)?;
+        fs::create_dir_all(&self.config.input_cache)?;
+        fs::write(self.cached(resource), &data)?;
+        Ok(data)
     }
 
-    fn read_form_disc(&self) -> io::Result<String> {
-        todo!()
+    fn read_form_disc(&self, resource: Resource) -> io::Result<String> {
+        let names = resource.file_names();
+        names.iter()
+            .map(|name| fs::read_to_string(self.config.input_cache.join(name)))
+            .find(Result::is_ok)
+            .unwrap_or_else(|| Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("none of {} cached in {}", names.join(", "), self.config.input_cache.display()),
+            )))
     }
 
     fn chain_of_command() -> impl Iterator<Item=Loader> {
         iter! [
-            Self::read_form_disc,
+            Self::read_form_disc as Loader,
             Self::fetch,
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("historian-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_small_vec_spills() {
+        let mut vec = (0..3).collect::<collections::SmallVec<u8, 3>>();
+        assert!(!vec.is_spilled());
+        vec.push(3);
+        assert!(vec.is_spilled());
+        assert_eq!(&*vec, &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_throttle_without_history() {
+        let throttle = Throttle::new(&scratch("throttle-fresh"), Duration::from_secs(60));
+        assert_eq!(throttle.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_throttle_persists_last_request() {
+        let cache = scratch("throttle-persist");
+        Throttle::new(&cache, Duration::from_secs(60)).wait().unwrap();
+
+        let remaining = Throttle::new(&cache, Duration::from_secs(60)).remaining();
+        assert!(remaining > Duration::from_secs(50));
+        assert_eq!(Throttle::new(&cache, Duration::ZERO).remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_offline_uses_cache_only() {
+        let cache = scratch("offline");
+        let manager = InputManager::with_config(Config::from_env().input_cache(&cache).offline(true));
+
+        let err = manager.input(1, 1).unwrap_err();
+        assert!(format!("{err:?}").contains("offline mode"));
+
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join("input-1.txt"), "3   4\n").unwrap();
+        assert_eq!(manager.input(1, 1).unwrap(), "3   4\n");
+
+        fs::write(cache.join("input-1-2.txt"), "4   3\n").unwrap();
+        assert_eq!(manager.input(1, 1).unwrap(), "3   4\n");
+        assert_eq!(manager.input(1, 2).unwrap(), "4   3\n");
+    }
+
+    #[test]
+    fn test_fetch_rereads_cache() {
+        let cache = scratch("fetch");
+        let manager = InputManager::with_config(Config::from_env().input_cache(&cache).offline(true));
+        let resource = Resource::Input { day: 2, part: 1 };
+        assert!(manager.fetch(resource).is_err());
+
+        // as left behind by a parallel task fetching the other part
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join("input-2.txt"), "1 2 3\n").unwrap();
+        assert_eq!(manager.fetch(resource).unwrap(), "1 2 3\n");
+    }
+
+    #[test]
+    fn test_puzzle_from_cache() {
+        let cache = scratch("puzzle");
+        let manager = InputManager::with_config(Config::from_env().input_cache(&cache).offline(true));
+        assert!(manager.puzzle(1).is_err());
+
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join("puzzle-1.html"), "<main><article><p>For example:</p><pre><code>1 2\n</code></pre></article></main>").unwrap();
+        assert_eq!(manager.puzzle(1).unwrap().examples(), ["1 2\n"]);
+    }
+}
@@ -1,25 +1,36 @@
 
+pub struct Part1;
+
+pub struct Part2;
+
 pub mod part1 {
-    use crate::Day;
+    use eyre::WrapErr;
+
+    use crate::core;
 
-    pub(super) fn parse(input: &str) -> (Vec<u32>, Vec<u32>) {
+    pub(super) fn parse(input: &str) -> eyre::Result<(Vec<u32>, Vec<u32>)> {
         let mut first = Vec::new();
         let mut second = Vec::new();
-        for line in input.lines() {
+        for (index, line) in input.lines().enumerate() {
             let mut parsed = line.split_whitespace();
-            first.push(parsed.next().expect("missing first number").parse().expect("first element is not a number"));
-            second.push(parsed.next().expect("missing second number").parse().expect("second element is a number"));
+            let (Some(lhs), Some(rhs)) = (parsed.next(), parsed.next()) else {
+                eyre::bail!("line {}: expected two numbers", index + 1);
+            };
+            first.push(lhs.parse().wrap_err_with(|| format!("line {}: first element is not a number", index + 1))?);
+            second.push(rhs.parse().wrap_err_with(|| format!("line {}: second element is not a number", index + 1))?);
         }
-        (first, second)
+        Ok((first, second))
     }
-    
-    pub fn solve() {
-        let day = Day::new(1, 1);
-        let (mut first, mut second) = parse(&day.input());
-        first.sort();
-        second.sort();
-        let result: u32 = first.iter().zip(second).map(|(first, second)| first.abs_diff(second)).sum();
-        day.report(result);
+
+    impl core::Solution<1, 1> for super::Part1 {
+        type Result = u32;
+
+        fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+            let (mut first, mut second) = parse(input)?;
+            first.sort();
+            second.sort();
+            Ok(first.iter().zip(second).map(|(first, second)| first.abs_diff(second)).sum())
+        }
     }
 }
 
@@ -27,33 +38,51 @@ pub mod part1 {
 pub mod part2 {
     use std::{collections::HashMap, ops::{Deref, Mul}};
 
-    use crate::Day;
+    use crate::core;
 
     use super::part1::parse;
 
-    pub fn solve() {
-        let day = Day::new(1, 2);
-
-        let (first, second) = parse(&day.input());
-
-        let mut counter = second
-            .iter()
-            .fold(
-                HashMap::<u32, u32>::with_capacity(second.len()), 
-                |mut counter, number| {
-                    counter.entry(*number)
-                        .and_modify(|count| *count += 1)
-                        .or_insert(1);
-                    counter
-                }
-            );
-
-        let result: u32 = first.into_iter()
-            .map(move |num| counter.entry(num)
-                .or_default()
-                .deref()
-                .mul(num)
-            ).sum();
-        day.report(result);
+    impl core::Solution<1, 2> for super::Part2 {
+        type Result = u32;
+
+        fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+            let (first, second) = parse(input)?;
+
+            let mut counter = second
+                .iter()
+                .fold(
+                    HashMap::<u32, u32>::with_capacity(second.len()),
+                    |mut counter, number| {
+                        counter.entry(*number)
+                            .and_modify(|count| *count += 1)
+                            .or_insert(1);
+                        counter
+                    }
+                );
+
+            Ok(first.into_iter()
+                .map(move |num| counter.entry(num)
+                    .or_default()
+                    .deref()
+                    .mul(num)
+                ).sum())
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Solution;
+
+    const EXAMPLE: &str = "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n";
+
+    #[test]
+    fn test_part1() { assert_eq!(Part1::solve(EXAMPLE), 11) }
+
+    #[test]
+    fn test_part2() { assert_eq!(Part2::solve(EXAMPLE), 31) }
+
+    #[test]
+    fn test_parse_error() { assert!(Part1::try_solve("3   4\n4\n").is_err()) }
+}
@@ -1,109 +1,94 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque}, hash::Hash, str::FromStr};
 
-use crate::core;
+use crate::{core, grid::{Coords, Direction, Point, Vec2d}};
 use arrayvec::ArrayVec;
 
-use crate::Day;
+/// What a map is made of, plots with equal cells belong to the same region when adjacent.
+///
+/// Usually a `char`, but any token works. Cells are `Send` and `Sync` for [`Labelling::Tiled`] to
+/// share the map between threads and hand regions back from them.
+pub trait Cell: Clone + Eq + Hash + Send + Sync {}
 
-type RegionId = u8;
+impl<C: Clone + Eq + Hash + Send + Sync> Cell for C {}
 
-#[derive(Debug, Clone, Copy)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Coords {
-    x: u16,
-    y: u16,
-}
-
-impl From<(u16, u16)> for Coords {
-    fn from((x, y): (u16, u16)) -> Self {
-        Self { x, y }
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Plot {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plot<C = char> {
     coords: Coords,
-    id: RegionId,
+    id: C,
 }
 
-pub struct Vec2d<T> {
-    width: usize,
-    height: usize,
-    data: Vec<T>,
+#[derive(Debug)]
+pub struct Map<C = char> {
+    data: Vec2d<C>
 }
 
-impl<T> Vec2d<T> {
-    pub fn new_from_default(width: usize, height: usize) -> Self
-    where
-        T: Default + Clone
-    {
-        Self {
-            width,
-            height,
-            data: vec![T::default(); width * height],
+impl<C: Cell> Map<C> {
+    pub fn plot(&self, coords: Coords) -> Plot<C> {
+        Plot {
+            coords,
+            id: self.data[coords].clone(),
         }
     }
 
-    pub fn from_slice(data: &[T], stride: usize) -> Self
-    where
-        T: Clone
-    {
-        assert!(data.len() % stride == 0);
-        Self {
-            width: stride,
-            height: data.len() / stride,
-            data: Vec::from(data),
-        }
+    /// Map from rows of cells, failing on rows of a different length than the first one.
+    pub fn from_rows<R: IntoIterator<Item = C>>(rows: impl IntoIterator<Item = R>) -> eyre::Result<Self> {
+        Ok(Self { data: Vec2d::from_rows(rows)? })
     }
 }
 
-impl<T> std::ops::Index<Coords> for Vec2d<T> {
-    type Output = T;
-
-    fn index(&self, Coords { x, y }: Coords) -> &Self::Output {
-        &self.data[x as usize + y as usize * self.width]
-    }
-}
+impl Map {
+    /// Deterministic pseudo-random map of `kinds` plant types from `A` on, at most 26, for tests and benchmarks.
+    ///
+    /// Plots mostly take after the plot above or to the left of them, so regions grow into
+    /// blobs of all sizes instead of single plots.
+    pub fn random(width: usize, height: usize, kinds: u8, seed: u64) -> Self {
+        let mut random = core::bench::Lcg::new(seed);
+        let kinds = u32::from(kinds.clamp(1, 26));
 
-impl<T> std::ops::IndexMut<Coords> for Vec2d<T> {
-    fn index_mut(&mut self, Coords { x, y }: Coords) -> &mut Self::Output {
-        &mut self.data[x as usize + y as usize * self.width]
+        let mut data = Vec::<char>::with_capacity(width * height);
+        for index in 0..width * height {
+            let (x, y) = (index % width, index / width);
+            let cell = match random.next_u32() % 8 {
+                0..=2 if y > 0 => data[index - width],
+                3..=5 if x > 0 => data[index - 1],
+                _ => char::from(b'A' + (random.next_u32() % kinds) as u8),
+            };
+            data.push(cell);
+        }
+        Self { data: Vec2d::from_vec(data, width) }
     }
 }
 
-pub struct Map {
-    data: Vec2d<RegionId>
-}
+/// Map of characters, one row per line.
+impl FromStr for Map {
+    type Err = eyre::Report;
 
-impl Map {
-    pub fn plot(&self, coords: Coords) -> Plot {
-        Plot {
-            coords,
-            id: self.data[coords],
-        }
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::from_rows(input.lines().map(str::chars))
     }
 }
 
-pub struct Region {
-    id: RegionId,
+pub struct Region<C = char> {
+    id: C,
+    plots: Vec<Coords>,
     pwbs: Vec<Pwb>,
 }
 
-impl Region {
-    pub fn new(id: RegionId, pwbs: Vec<Pwb>) -> Self {
+impl<C> Region<C> {
+    /// `plots` and `pwbs` describe the same plots, in the same order.
+    pub fn new(id: C, plots: Vec<Coords>, pwbs: Vec<Pwb>) -> Self {
+        debug_assert_eq!(plots.len(), pwbs.len());
         Self {
             id,
+            plots,
             pwbs,
         }
     }
 
+    pub fn plots(&self) -> &[Coords] {
+        &self.plots
+    }
+
     pub fn area(&self) -> usize {
         self.pwbs.len()
     }
@@ -116,44 +101,118 @@ impl Region {
         self.area() * self.perimeter()
     }
 
+    /// Number of straight fence sides, equal to the number of corners of the region's outline.
+    ///
+    /// Every plot checks its four diagonal quadrants: with both orthogonal neighbors outside
+    /// the region it has a convex corner there, with both inside but the diagonal one outside
+    /// a concave one.
+    pub fn sides(&self) -> usize {
+        let plots = self.plots.iter().copied().map(Point::from).collect::<HashSet<_>>();
+        let inside = |point| plots.contains(&point);
+
+        plots.iter()
+            .flat_map(|&plot| Direction::DIAGONAL.map(|diagonal| (plot, diagonal.offset())))
+            .filter(|&(plot, Point { x: dx, y: dy })| {
+                let horizontal = inside(plot + Point::new(dx, 0));
+                let vertical = inside(plot + Point::new(0, dy));
+                (!horizontal && !vertical) || (horizontal && vertical && !inside(plot + Point::new(dx, dy)))
+            })
+            .count()
+    }
+
     pub fn discounted_price(&self) -> usize {
+        self.area() * self.sides()
+    }
+
+    /// Smallest box containing every plot of the region.
+    pub fn bounding_box(&self) -> BoundingBox {
+        let first = self.plots[0];
+        self.plots.iter().fold(BoundingBox { min: first, max: first }, |BoundingBox { min, max }, plot| BoundingBox {
+            min: Coords { x: min.x.min(plot.x), y: min.y.min(plot.y) },
+            max: Coords { x: max.x.max(plot.x), y: max.y.max(plot.y) },
+        })
+    }
 
+    /// Mean of the plot coordinates, which need not lie inside the region.
+    pub fn centroid(&self) -> (f64, f64) {
+        let (x, y) = self.plots.iter().fold((0.0, 0.0), |(x, y), plot| (x + plot.x as f64, y + plot.y as f64));
+        (x / self.area() as f64, y / self.area() as f64)
     }
 }
 
-impl Map {
+/// Inclusive range of coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: Coords,
+    pub max: Coords,
+}
+
+impl BoundingBox {
     pub fn width(&self) -> usize {
-        self.data.width
+        (self.max.x - self.min.x) as usize + 1
     }
 
     pub fn height(&self) -> usize {
-        self.data.height
+        (self.max.y - self.min.y) as usize + 1
+    }
+
+    pub fn contains(&self, coords: Coords) -> bool {
+        (self.min.x..=self.max.x).contains(&coords.x) && (self.min.y..=self.max.y).contains(&coords.y)
+    }
+}
+
+/// Side of a plot facing another region or the edge of the map, every fence segment is one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorderEdge {
+    pub coords: Coords,
+    pub direction: Direction,
+    pub inside: Label,
+    /// Region across the edge, `None` past the edge of the map.
+    pub outside: Option<Label>,
+}
+
+/// Which regions border which, and along how many plot edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjacency {
+    neighbors: Vec<BTreeMap<Label, usize>>,
+}
+
+impl Adjacency {
+    /// Regions bordering `label` with the length of the shared border, by label.
+    pub fn neighbors(&self, label: Label) -> impl Iterator<Item = (Label, usize)> + '_ {
+        self.neighbors[label as usize].iter().map(|(&label, &length)| (label, length))
+    }
+
+    /// Length of the border between two regions, zero if they do not touch.
+    pub fn shared_edge(&self, lhs: Label, rhs: Label) -> usize {
+        self.neighbors[lhs as usize].get(&rhs).copied().unwrap_or(0)
+    }
+}
+
+impl<C: Cell> Map<C> {
+    pub fn width(&self) -> usize {
+        self.data.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.data.height()
     }
 
     fn move_coords(&self, coords: Coords, direction: Direction) -> Option<Coords> {
-        match direction {
-            Direction::Up    if coords.y > 0 => Some(Coords { y: coords.y - 1, ..coords }),
-            Direction::Down  if coords.y < self.height() as u16 - 1 => Some(Coords { y: coords.y + 1, ..coords }),
-            Direction::Left  if coords.x > 0 => Some(Coords { x: coords.x - 1, ..coords }),
-            Direction::Right if coords.x < self.width() as u16 - 1 => Some(Coords { x: coords.x + 1, ..coords }),
-            _ => None
-        }
+        self.data.step(coords, direction)
     }
 
-    fn plot_neighbors(&self, plot: Plot) -> impl Iterator<Item = Coords> + '_ {
+    fn plot_neighbors(&self, plot: &Plot<C>) -> impl Iterator<Item = Coords> + '_ {
+        let coords = plot.coords;
         [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
             .into_iter()
-            .filter_map(move |direction| self.move_coords(plot.coords, direction))
+            .filter_map(move |direction| self.move_coords(coords, direction))
     }
 
-    pub fn neighbors(&self, plot: &Plot) -> ArrayVec<Plot, 4> {
-        self.plot_neighbors(*plot)
-            .into_iter()
-            .filter_map(|coords|
-                self.is_in_bounds(&coords)
-                    .then(|| Plot { coords, id: self.data[coords] })
-                    .filter(|neighbor| neighbor.id == plot.id)
-            )
+    pub fn neighbors(&self, plot: &Plot<C>) -> ArrayVec<Plot<C>, 4> {
+        self.plot_neighbors(plot)
+            .filter(|&coords| self.is_in_bounds(&coords) && self.data[coords] == plot.id)
+            .map(|coords| self.plot(coords))
             .collect::<ArrayVec<_, 4>>()
     }
 
@@ -161,13 +220,41 @@ impl Map {
         coordinates.x < self.width() as u16 && coordinates.y < self.height() as u16
     }
 
-    pub fn graph(&self) -> MapGraph {
+    pub fn graph(&self) -> MapGraph<C> {
         MapGraph::new(self)
     }
+
+    pub fn graph_with(&self, labelling: Labelling) -> MapGraph<C> {
+        MapGraph::with_labelling(self, labelling)
+    }
+
+    pub fn navigator(&self) -> MapNavigator<'_, C> {
+        MapNavigator::new(&self.data)
+    }
+}
+
+/// Index of a region in discovery order, regions are discovered by their first plot in row-major order.
+pub type Label = u32;
+
+/// How [`MapGraph`] finds the regions of a map, both backends label regions the same way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Labelling {
+    /// Depth-first search from every plot not visited yet.
+    #[default]
+    Dfs,
+    /// Two passes over the map, merging plots with their left and upper neighbors in a
+    /// union-find forest and then numbering the roots.
+    UnionFind,
+    /// Like [`Labelling::UnionFind`], with `strips` horizontal strips labelled in parallel and
+    /// only the regions meeting at the strip borders merged afterwards.
+    Tiled { strips: usize },
 }
 
-pub struct MapGraph {
-    components: HashMap<RegionId, Vec<Region>>,
+pub struct MapGraph<C = char> {
+    components: HashMap<C, Vec<Region<C>>>,
+    labels: Vec2d<Label>,
+    /// Position of every label's region in `components`.
+    regions: Vec<(C, usize)>,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -176,75 +263,471 @@ pub struct PlotWithBorder {
 }
 type Pwb = PlotWithBorder;
 
-impl MapGraph {
-    fn components(&self) -> &HashMap<RegionId, Vec<Region>> {
+impl<C: Cell> MapGraph<C> {
+    fn components(&self) -> &HashMap<C, Vec<Region<C>>> {
         &self.components
     }
-    
-    fn explore_region(map: &Map, start: &Plot, visited: &mut Vec2d<bool>) -> Region {
-        let mut remaining = Vec::from([*start]);
+
+    /// Label of the region every plot is in.
+    pub fn labels(&self) -> &Vec2d<Label> {
+        &self.labels
+    }
+
+    pub fn label(&self, coords: Coords) -> Label {
+        self.labels[coords]
+    }
+
+    pub fn region(&self, label: Label) -> &Region<C> {
+        let (id, index) = &self.regions[label as usize];
+        &self.components[id][*index]
+    }
+
+    /// The region `coords` is in.
+    pub fn region_at(&self, coords: Coords) -> &Region<C> {
+        self.region(self.label(coords))
+    }
+
+    /// Number of regions, labels go from zero up to it.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Every border edge of every region, plot by plot in row-major order.
+    ///
+    /// Edges between two regions come up twice, once from each side.
+    pub fn border_edges(&self) -> impl Iterator<Item = BorderEdge> + '_ {
+        self.labels.coords().flat_map(move |coords| {
+            let inside = self.labels[coords];
+            Direction::ORTHOGONAL.into_iter().filter_map(move |direction| {
+                let outside = self.labels.step(coords, direction).map(|coords| self.labels[coords]);
+                (outside != Some(inside)).then_some(BorderEdge { coords, direction, inside, outside })
+            })
+        })
+    }
+
+    pub fn adjacency(&self) -> Adjacency {
+        let mut neighbors = vec![BTreeMap::new(); self.len()];
+        for edge in self.border_edges() {
+            if let Some(outside) = edge.outside {
+                *neighbors[edge.inside as usize].entry(outside).or_insert(0) += 1;
+            }
+        }
+        Adjacency { neighbors }
+    }
+
+    /// The region surrounding `label` on all sides, if a single one does.
+    ///
+    /// Only the outer boundary counts, whatever fills the holes of a region does not keep it from
+    /// being enclosed. Regions touching the edge of the map are never enclosed.
+    pub fn enclosing(&self, label: Label) -> Option<Label> {
+        let BoundingBox { min, max } = self.region(label).bounding_box();
+        if min.x == 0 || min.y == 0 || max.x as usize + 1 == self.labels.width() || max.y as usize + 1 == self.labels.height() {
+            return None;
+        }
+        // every plot past the bounding box reaches the map edge without crossing the region,
+        // so flooding the box widened by one from its rim finds exactly the outer boundary
+        let frame = BoundingBox {
+            min: Coords { x: min.x - 1, y: min.y - 1 },
+            max: Coords { x: max.x + 1, y: max.y + 1 },
+        };
+        let mut visited = HashSet::new();
+        let mut remaining = (frame.min.x..=frame.max.x)
+            .flat_map(|x| [frame.min.y, frame.max.y].map(|y| Coords { x, y }))
+            .chain((frame.min.y..=frame.max.y).flat_map(|y| [frame.min.x, frame.max.x].map(|x| Coords { x, y })))
+            .collect::<Vec<_>>();
+        let mut enclosing = None;
+        while let Some(coords) = remaining.pop() {
+            if !visited.insert(coords) {
+                continue;
+            }
+            for neighbor in Direction::ORTHOGONAL.into_iter().filter_map(|direction| self.labels.step(coords, direction)) {
+                if self.labels[neighbor] == label {
+                    if *enclosing.get_or_insert(self.labels[coords]) != self.labels[coords] {
+                        return None;
+                    }
+                } else if frame.contains(neighbor) && !visited.contains(&neighbor) {
+                    remaining.push(neighbor);
+                }
+            }
+        }
+        enclosing
+    }
+
+    /// Regions fully surrounded by a single other region, paired with the surrounding one.
+    ///
+    /// See [`MapGraph::enclosing`] for what counts as surrounded.
+    pub fn enclosed(&self) -> impl Iterator<Item = (Label, Label)> + '_ {
+        (0..self.len() as Label).filter_map(|label| Some((label, self.enclosing(label)?)))
+    }
+
+    fn explore_region(map: &Map<C>, start: &Plot<C>, label: Label, labels: &mut Vec2d<Label>, visited: &mut Vec2d<bool>) -> Region<C> {
+        let mut remaining = Vec::from([start.clone()]);
+        let mut plots = Vec::with_capacity(32);
         let mut pwbs = Vec::with_capacity(32);
-        let id = start.id;
+        let id = start.id.clone();
 
         while let Some(current) = remaining.pop() {
             if !visited[current.coords] {  // visit
                 visited[current.coords] = true;
+                labels[current.coords] = label;
                 let neighbors = map.neighbors(&current);
+                plots.push(current.coords);
                 pwbs.push(PlotWithBorder { n_neighbors: neighbors.len() as _ });
-                remaining.extend(neighbors.into_iter());
+                remaining.extend(neighbors);
             }
         }
-        Region { id, pwbs }
+        Region::new(id, plots, pwbs)
+    }
+
+    pub fn new(map: &Map<C>) -> Self {
+        Self::with_labelling(map, Labelling::default())
+    }
+
+    pub fn with_labelling(map: &Map<C>, labelling: Labelling) -> Self {
+        let (labels, regions) = match labelling {
+            Labelling::Dfs => Self::label_dfs(map),
+            Labelling::UnionFind => Self::label_union_find(map),
+            Labelling::Tiled { strips } => Self::label_tiled(map, strips),
+        };
+        Self::assemble(labels, regions)
     }
 
-    pub fn new(map: &Map) -> Self {
+    fn label_dfs(map: &Map<C>) -> (Vec2d<Label>, Vec<Region<C>>) {
         let mut visited = Vec2d::<bool>::new_from_default(map.width(), map.height());
-        let mut components = HashMap::new();
+        let mut labels = Vec2d::<Label>::new_from_default(map.width(), map.height());
+        let mut regions = Vec::new();
 
         // keep looking tough plots
-        for y in 0..map.height() as u16 {
-            for x in 0..map.width() as u16 {
-                let coords = (x, y).into();
-                if !visited[coords] {
-                    // visit
-                    let start = map.plot(coords);
-                    components.entry(start.id)
-                        .or_insert(Vec::new())
-                        .push(Self::explore_region(map, &start, &mut visited));
-                }
+        for coords in map.data.coords() {
+            if !visited[coords] {
+                // visit
+                let start = map.plot(coords);
+                let label = regions.len() as Label;
+                regions.push(Self::explore_region(map, &start, label, &mut labels, &mut visited));
             }
         }
 
-        Self { components }
+        (labels, regions)
     }
-}
 
-impl<'a> FromIterator<&'a str> for Map {
-    fn from_iter<T: IntoIterator<Item = &'a str>>(iter: T) -> Self {
-        let mut stride = None;
-        let map = iter.into_iter()
-            .flat_map(|line| {
-                stride.get_or_insert(line.len());
-                line.bytes()
+    fn label_union_find(map: &Map<C>) -> (Vec2d<Label>, Vec<Region<C>>) {
+        Self::label_strip(map, 0..map.height())
+    }
+
+    fn label_tiled(map: &Map<C>, strips: usize) -> (Vec2d<Label>, Vec<Region<C>>) {
+        let (width, height) = (map.width(), map.height());
+        let strips = strips.clamp(1, height.max(1));
+        if strips == 1 {
+            return Self::label_union_find(map);
+        }
+        let bounds = (0..strips)
+            .map(|strip| strip * height / strips..(strip + 1) * height / strips)
+            .collect::<Vec<_>>();
+
+        let mut strips = std::thread::scope(|scope| {
+            bounds.iter()
+                .map(|rows| scope.spawn(|| Self::label_strip(map, rows.clone())))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().expect("labelling a strip panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        // regions of all strips, strip after strip, merged across the borders
+        let offsets = strips.iter()
+            .scan(0, |offset, (_, regions)| {
+                let start = *offset;
+                *offset += regions.len();
+                Some(start)
             })
             .collect::<Vec<_>>();
+        let mut forest = UnionFind::new(strips.iter().map(|(_, regions)| regions.len()).sum());
+        for (strip, rows) in bounds.iter().enumerate().skip(1) {
+            let (above, below) = (&strips[strip - 1].0, &strips[strip].0);
+            let y = rows.start as u16;
+            for x in 0..width as u16 {
+                if map.data[Coords { x, y }] == map.data[Coords { x, y: y - 1 }] {
+                    let last = Coords { x, y: above.height() as u16 - 1 };
+                    forest.union(offsets[strip - 1] + above[last] as usize, offsets[strip] + below[Coords { x, y: 0 }] as usize);
+                }
+            }
+        }
 
-        Self {
-            data: Vec2d::from_slice(&map, stride.unwrap()),
+        // a root is the strip region holding the first plot of the merged one, so roots come in discovery order
+        let mut relabel = Vec::<Label>::with_capacity(forest.parents.len());
+        let mut regions = Vec::<Region<C>>::new();
+        for (index, part) in strips.iter_mut().flat_map(|(_, regions)| regions.drain(..)).enumerate() {
+            let root = forest.find(index);
+            if root == index {
+                relabel.push(regions.len() as Label);
+                regions.push(part);
+            } else {
+                let label = relabel[root];
+                relabel.push(label);
+                let region = &mut regions[label as usize];
+                region.plots.extend(part.plots);
+                region.pwbs.extend(part.pwbs);
+            }
+        }
+
+        let relabel = &relabel;
+        let labels = std::thread::scope(|scope| {
+            strips.into_iter()
+                .zip(offsets)
+                .map(|((labels, _), offset)| scope.spawn(move || {
+                    labels.iter().map(|&label| relabel[offset + label as usize]).collect::<Vec<_>>()
+                }))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("relabelling a strip panicked"))
+                .collect()
+        });
+
+        (Vec2d::from_vec(labels, width), regions)
+    }
+
+    /// Labels of the plots in `rows` and the regions they form within the strip, indexed from
+    /// the first row of the strip and numbered in its own discovery order.
+    fn label_strip(map: &Map<C>, rows: std::ops::Range<usize>) -> (Vec2d<Label>, Vec<Region<C>>) {
+        let width = map.width();
+        let mut forest = Self::strip_forest(map, rows.clone());
+
+        // roots are the first plot of their region, so they are numbered in discovery order
+        let mut labels = Vec2d::<Label>::new_from_default(width, rows.len());
+        let mut regions = Vec::<Region<C>>::new();
+        for (index, local) in labels.coords().enumerate() {
+            let coords = Coords { y: local.y + rows.start as u16, ..local };
+            let root = forest.find(index);
+            let label = if root == index {
+                regions.push(Region::new(map.data[coords].clone(), Vec::new(), Vec::new()));
+                regions.len() as Label - 1
+            } else {
+                labels[Coords { x: (root % width) as u16, y: (root / width) as u16 }]
+            };
+            labels[local] = label;
+            let region = &mut regions[label as usize];
+            region.plots.push(coords);
+            region.pwbs.push(PlotWithBorder { n_neighbors: map.neighbors(&map.plot(coords)).len() as _ });
+        }
+
+        (labels, regions)
+    }
+
+    /// Union-find forest of the plots in `rows`, indexed from the first plot of the strip.
+    fn strip_forest(map: &Map<C>, rows: std::ops::Range<usize>) -> UnionFind {
+        let width = map.width();
+        let mut forest = UnionFind::new(width * rows.len());
+
+        for (y, row) in rows.clone().map(|y| (y, map.data.row(y))) {
+            let above = (y > rows.start).then(|| map.data.row(y - 1));
+            let start = (y - rows.start) * width;
+            for (x, id) in row.iter().enumerate() {
+                let index = start + x;
+                if x > 0 && row[x - 1] == *id {
+                    forest.union(index - 1, index);
+                }
+                if above.is_some_and(|above| above[x] == *id) {
+                    forest.union(index - width, index);
+                }
+            }
+        }
+        forest
+    }
+
+    fn assemble(labels: Vec2d<Label>, regions: Vec<Region<C>>) -> Self {
+        let mut components = HashMap::<C, Vec<Region<C>>>::new();
+        let regions = regions
+            .into_iter()
+            .map(|region| {
+                let id = region.id.clone();
+                let component = components.entry(id.clone()).or_default();
+                component.push(region);
+                (id, component.len() - 1)
+            })
+            .collect();
+        Self { components, labels, regions }
+    }
+}
+
+/// What [`MapGraph::render`] draws besides the plots.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Fences between regions, drawn on a grid twice as fine as the map.
+    pub fences: bool,
+    /// A line per region with its measurements.
+    pub legend: bool,
+}
+
+/// Colour of the region with `label`, hues spread by the golden ratio so that regions with close
+/// labels, which tend to be close on the map as well, look different.
+pub fn region_color(label: Label) -> (u8, u8, u8) {
+    let hue = (label as f64 * 0.618_033_988_75).fract() * 6.0;
+    let (saturation, value) = (0.55, 0.95);
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |channel: f64| ((channel + value - chroma) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Rendering writes every plot's cell as is, fences only line up for cells displayed as a single character.
+impl<C: Cell + std::fmt::Display> MapGraph<C> {
+    fn is_fence(&self, coords: Coords, direction: Direction) -> bool {
+        self.labels.step(coords, direction).is_none_or(|neighbor| self.labels[neighbor] != self.labels[coords])
+    }
+
+    /// The map for the terminal, every region in its own colour.
+    pub fn render(&self, options: RenderOptions) -> String {
+        use colored::Colorize;
+
+        let plot = |coords: Coords| {
+            let label = self.labels[coords];
+            let (r, g, b) = region_color(label);
+            self.region(label).id.to_string().black().on_truecolor(r, g, b).to_string()
+        };
+
+        let mut out = String::new();
+        if options.fences {
+            let (width, height) = (self.labels.width() as u16, self.labels.height() as u16);
+            // plots sit on odd rows and columns, fences and their corners on even ones
+            for row in 0..=2 * height {
+                for column in 0..=2 * width {
+                    let coords = Coords { x: column / 2, y: row / 2 };
+                    let above = Coords { y: coords.y.wrapping_sub(1), ..coords };
+                    let left = Coords { x: coords.x.wrapping_sub(1), ..coords };
+                    let horizontal = |coords: Coords| match (self.labels.contains(coords), coords.y < height) {
+                        (true, _) => self.is_fence(coords, Direction::Up),
+                        (false, below_map) => !below_map && coords.x < width,
+                    };
+                    let vertical = |coords: Coords| match self.labels.contains(coords) {
+                        true => self.is_fence(coords, Direction::Left),
+                        false => coords.x == width && coords.y < height,
+                    };
+                    out.push_str(&match (row % 2, column % 2) {
+                        (1, 1) => plot(coords),
+                        (0, 1) if horizontal(coords) => "-".to_owned(),
+                        (1, 0) if vertical(coords) => "|".to_owned(),
+                        (0, 0) if horizontal(coords) || horizontal(left) || vertical(coords) || vertical(above) => "+".to_owned(),
+                        _ => " ".to_owned(),
+                    });
+                }
+                out.push('\n');
+            }
+        } else {
+            for y in 0..self.labels.height() as u16 {
+                for x in 0..self.labels.width() as u16 {
+                    out.push_str(&plot(Coords { x, y }));
+                }
+                out.push('\n');
+            }
+        }
+
+        if options.legend {
+            for label in 0..self.len() as Label {
+                let region = self.region(label);
+                let (r, g, b) = region_color(label);
+                out.push_str(&format!(
+                    "{} #{label} {}: area {}, perimeter {}, price {}, sides {}, discounted price {}\n",
+                    "  ".on_truecolor(r, g, b),
+                    region.id,
+                    region.area(),
+                    region.perimeter(),
+                    region.price(),
+                    region.sides(),
+                    region.discounted_price(),
+                ));
+            }
+        }
+        out
+    }
+
+    /// The map as a binary PPM image, every plot a `scale` pixels wide square in its region's
+    /// colour, with black fences along region borders if `fences` is set.
+    pub fn write_ppm(&self, mut writer: impl std::io::Write, scale: usize, fences: bool) -> std::io::Result<()> {
+        let scale = scale.max(1);
+        let (width, height) = (self.labels.width() * scale, self.labels.height() * scale);
+        write!(writer, "P6\n{width} {height}\n255\n")?;
+
+        let mut row = Vec::with_capacity(width * 3);
+        for y in 0..height {
+            row.clear();
+            for x in 0..width {
+                let coords = Coords { x: (x / scale) as u16, y: (y / scale) as u16 };
+                let (column, line) = (x % scale, y % scale);
+                let fence = fences && scale > 1 && [
+                    (line == 0, Direction::Up),
+                    (line == scale - 1, Direction::Down),
+                    (column == 0, Direction::Left),
+                    (column == scale - 1, Direction::Right),
+                ].into_iter().any(|(edge, direction)| edge && self.is_fence(coords, direction));
+                let (r, g, b) = if fence { (0, 0, 0) } else { region_color(self.labels[coords]) };
+                row.extend([r, g, b]);
+            }
+            writer.write_all(&row)?;
+        }
+        writer.flush()
+    }
+
+    pub fn save_ppm(&self, path: impl AsRef<std::path::Path>, scale: usize, fences: bool) -> std::io::Result<()> {
+        self.write_ppm(std::io::BufWriter::new(std::fs::File::create(path)?), scale, fences)
+    }
+}
+
+/// Disjoint sets of `0..len`, the smaller root wins a union so every root is its set's minimum.
+struct UnionFind {
+    parents: Vec<u32>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self { parents: (0..len as u32).collect() }
+    }
+
+    fn find(&mut self, mut element: usize) -> usize {
+        // path halving
+        while self.parents[element] as usize != element {
+            let grandparent = self.parents[self.parents[element] as usize];
+            self.parents[element] = grandparent;
+            element = grandparent as usize;
         }
+        element
+    }
+
+    fn union(&mut self, lhs: usize, rhs: usize) {
+        let (lhs, rhs) = (self.find(lhs), self.find(rhs));
+        let (root, child) = (lhs.min(rhs), lhs.max(rhs));
+        self.parents[child] = root as u32;
+    }
+}
+
+/// Map of characters from its lines, panics on ragged rows, [`Map::from_str`] reports them instead.
+impl<'a> FromIterator<&'a str> for Map {
+    fn from_iter<T: IntoIterator<Item = &'a str>>(iter: T) -> Self {
+        Self::from_rows(iter.into_iter().map(str::chars)).unwrap_or_else(|error| panic!("{error}"))
     }
 }
 
 pub struct Part1;
 
-impl core::Solution<12> for Part1 {
+pub struct Part2;
+
+impl core::Solution<12, 1> for Part1 {
     type Result = u64;
 
-    fn try_solve() -> eyre::Result<Self::Result> {
-        let day = Day::new(12, 1);
-        Ok(day.input()?
-            .lines()
-            .collect::<Map>()
+    fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+        Ok(input
+            .parse::<Map>()?
             .graph()
             .components()
             .values()
@@ -257,15 +740,18 @@ impl core::Solution<12> for Part1 {
     }
 }
 
+impl core::Solution<12, 2> for Part2 {
+    type Result = u64;
 
-//
-//
-// 
-pub mod part1 {
-    use super::*;
-
-    pub fn solve() -> eyre::Result<>{
-        
+    fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+        Ok(input
+            .parse::<Map>()?
+            .graph()
+            .components()
+            .values()
+            .flatten()
+            .map(|region| region.discounted_price() as u64)
+            .sum::<u64>())
     }
 }
 
@@ -283,8 +769,143 @@ pub mod part1 {
 //
 //
 
-pub struct MapNavigator {
-    
+/// Shortest path between two plots, both ends included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    pub plots: Vec<Coords>,
+    pub cost: u64,
+}
+
+/// Rule for a step between two cells, given the cell it goes from and the one it goes to.
+type StepRule<'a, T, R> = Box<dyn Fn(&T, &T) -> R + 'a>;
+
+/// Shortest paths over a grid, stepping orthogonally between cells.
+///
+/// Whether a step is allowed and what it costs depend on the cells it goes from and to, every
+/// step is allowed and costs one by default.
+pub struct MapNavigator<'a, T> {
+    grid: &'a Vec2d<T>,
+    passable: StepRule<'a, T, bool>,
+    cost: StepRule<'a, T, u64>,
+}
+
+impl<'a, T> MapNavigator<'a, T> {
+    pub fn new(grid: &'a Vec2d<T>) -> Self {
+        Self {
+            grid,
+            passable: Box::new(|_, _| true),
+            cost: Box::new(|_, _| 1),
+        }
+    }
+
+    /// Allow only steps `passable(from, to)` holds for, e.g. `|from, to| from == to` to stay in a region.
+    pub fn passable(mut self, passable: impl Fn(&T, &T) -> bool + 'a) -> Self {
+        self.passable = Box::new(passable);
+        self
+    }
+
+    /// Price steps by `cost(from, to)`, [`MapNavigator::bfs`] and [`MapNavigator::distances`] ignore it.
+    pub fn cost(mut self, cost: impl Fn(&T, &T) -> u64 + 'a) -> Self {
+        self.cost = Box::new(cost);
+        self
+    }
+
+    fn steps(&self, from: Coords) -> impl Iterator<Item = Coords> + '_ {
+        self.grid
+            .neighbors4(from)
+            .filter(move |&to| (self.passable)(&self.grid[from], &self.grid[to]))
+    }
+
+    fn path(previous: &Vec2d<Option<Coords>>, goal: Coords, cost: u64) -> Path {
+        let mut plots = Vec::from([goal]);
+        while let Some(plot) = previous[*plots.last().unwrap()] {
+            plots.push(plot);
+        }
+        plots.reverse();
+        Path { plots, cost }
+    }
+
+    /// Path with the fewest steps, its cost is the number of steps.
+    pub fn bfs(&self, start: Coords, goal: Coords) -> Option<Path> {
+        let mut previous = Vec2d::<Option<Coords>>::new_from_default(self.grid.width(), self.grid.height());
+        let mut visited = Vec2d::<bool>::new_from_default(self.grid.width(), self.grid.height());
+        let mut queue = VecDeque::from([(start, 0)]);
+        visited[start] = true;
+
+        while let Some((current, steps)) = queue.pop_front() {
+            if current == goal {
+                return Some(Self::path(&previous, goal, steps));
+            }
+            for next in self.steps(current) {
+                if !std::mem::replace(&mut visited[next], true) {
+                    previous[next] = Some(current);
+                    queue.push_back((next, steps + 1));
+                }
+            }
+        }
+        None
+    }
+
+    /// Cheapest path.
+    pub fn dijkstra(&self, start: Coords, goal: Coords) -> Option<Path> {
+        self.a_star_with(start, goal, |_| 0)
+    }
+
+    /// Cheapest path, guided by the Manhattan distance to the goal.
+    ///
+    /// The distance only underestimates the cost, as A* needs it to, while every step costs at
+    /// least one. Cheaper steps need [`MapNavigator::a_star_with`] and a smaller estimate.
+    pub fn a_star(&self, start: Coords, goal: Coords) -> Option<Path> {
+        self.a_star_with(start, goal, |plot| Point::from(plot).manhattan(goal.into()))
+    }
+
+    /// Cheapest path, `estimate` must never exceed the actual cost of reaching the goal.
+    pub fn a_star_with(&self, start: Coords, goal: Coords, estimate: impl Fn(Coords) -> u64) -> Option<Path> {
+        let mut previous = Vec2d::<Option<Coords>>::new_from_default(self.grid.width(), self.grid.height());
+        let mut costs = Vec2d::<Option<u64>>::new_from_default(self.grid.width(), self.grid.height());
+        let mut queue = BinaryHeap::from([Reverse((estimate(start), 0, start))]);
+        costs[start] = Some(0);
+
+        while let Some(Reverse((_, cost, current))) = queue.pop() {
+            if current == goal {
+                return Some(Self::path(&previous, goal, cost));
+            }
+            if costs[current].is_some_and(|best| best < cost) {
+                continue;  // stale entry
+            }
+            for next in self.steps(current) {
+                let cost = cost + (self.cost)(&self.grid[current], &self.grid[next]);
+                if costs[next].is_none_or(|best| cost < best) {
+                    costs[next] = Some(cost);
+                    previous[next] = Some(current);
+                    queue.push(Reverse((cost + estimate(next), cost, next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Fewest steps from the nearest of `sources` to every cell, `None` where none of them reaches.
+    pub fn distances(&self, sources: impl IntoIterator<Item = Coords>) -> Vec2d<Option<u32>> {
+        let mut distances = Vec2d::<Option<u32>>::new_from_default(self.grid.width(), self.grid.height());
+        let mut queue = VecDeque::new();
+        for source in sources {
+            if distances[source].replace(0).is_none() {
+                queue.push_back(source);
+            }
+        }
+
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[current].map(|distance| distance + 1);
+            for next in self.steps(current) {
+                if distances[next].is_none() {
+                    distances[next] = distance;
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
 }
 
 // Region ID -> list of &Regions
@@ -298,12 +919,16 @@ pub struct MapNavigator {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    /// The puzzle's larger example, 1930 to fence and 1206 with the bulk discount.
+    const LARGER_EXAMPLE: &str = "RRRRIICCFF\nRRRRIICCCF\nVVRRRCCFFF\nVVRCCCJFFF\nVVVVCJJCFE\nVVIVCCJJEE\nVVIIICJJEE\nMIIIIIJJEE\nMIIISIJEEE\nMMMISSJEEE";
+
+    /// The puzzle's first example, one region per plant.
+    const SMALL_EXAMPLE: &str = "AAAA\nBBCD\nBBCC\nEEEC";
     
     #[test]
     pub fn test_map_from_iterator() {
-        let input = "RRRRIICCFF\nRRRRIICCCF\nVVRRRCCFFF\nVVRCCCJFFF\nVVVVCJJCFE\nVVIVCCJJEE\nVVIIICJJEE\nMIIIIIJJEE\nMIIISIJEEE\nMMMISSJEEE";
-
-        let map = input.lines().collect::<Map>();
+        let map = LARGER_EXAMPLE.lines().collect::<Map>();
         assert_eq!(map.width(), 10);
         println!("{}", map.height());
         assert_eq!(map.height(), 10);
@@ -311,30 +936,28 @@ mod tests {
 
     #[test]
     pub fn test_neighbors_1() {
-        let input = "RRRRIICCFF\nRRRRIICCCF\nVVRRRCCFFF\nVVRCCCJFFF\nVVVVCJJCFE\nVVIVCCJJEE\nVVIIICJJEE\nMIIIIIJJEE\nMIIISIJEEE\nMMMISSJEEE";
-
-        let map = input.lines().collect::<Map>();
+        let map = LARGER_EXAMPLE.lines().collect::<Map>();
         let coords = Coords {
             x: 1,
             y: 1,
         };
         let plot = Plot {
             coords,
-            id: b'R',
+            id: 'R',
         };
 
         assert_eq!(map.neighbors(&plot).into_iter().collect::<Vec<_>>(), &[
             Plot {
                 coords: Coords { x: 1, y: 0 },
-                id: b'R',
+                id: 'R',
             },
             Plot {
                 coords: Coords { x: 0, y: 1 },
-                id: b'R',
+                id: 'R',
             },
             Plot {
                 coords: Coords { x: 2, y: 1 },
-                id: b'R',
+                id: 'R',
             },
         ]);
     }
@@ -350,7 +973,7 @@ mod tests {
         };
         let plot = Plot {
             coords,
-            id: b'A',
+            id: 'A',
         };
 
         assert_eq!(
@@ -363,13 +986,11 @@ mod tests {
 
     #[test]
     pub fn test_neighbors() {
-        let input = "AAAA\nBBCD\nBBCC\nEEEC";
-
-        let graph = input.lines()
+        let graph = SMALL_EXAMPLE.lines()
             .collect::<Map>()
             .graph();
         let a_components = graph.components()
-            .get(&b'A').unwrap();
+            .get(&'A').unwrap();
         assert_eq!(a_components.len(), 1);
         assert_eq!(a_components[0].pwbs.as_slice(), [
             Pwb { n_neighbors: 1 },
@@ -384,9 +1005,9 @@ mod tests {
         let input = "OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO";
         let graph = input.lines().collect::<Map>().graph();
         assert_eq!(graph.components().len(), 2);
-        assert_eq!(graph.components()[&b'O'].len(), 1);
-        assert_eq!(graph.components()[&b'X'].len(), 4);
-        for region in &graph.components()[&b'X'] {
+        assert_eq!(graph.components()[&'O'].len(), 1);
+        assert_eq!(graph.components()[&'X'].len(), 4);
+        for region in &graph.components()[&'X'] {
             assert_eq!(region.area(), 1);
             assert_eq!(region.perimeter(), 4);
         }
@@ -394,8 +1015,7 @@ mod tests {
 
     #[test]
     fn test_price() {
-        let input = "RRRRIICCFF\nRRRRIICCCF\nVVRRRCCFFF\nVVRCCCJFFF\nVVVVCJJCFE\nVVIVCCJJEE\nVVIIICJJEE\nMIIIIIJJEE\nMIIISIJEEE\nMMMISSJEEE";
-        let price = input.lines()
+        let price = LARGER_EXAMPLE.lines()
             .collect::<Map>()
             .graph()
             .components()
@@ -409,9 +1029,321 @@ mod tests {
         assert_eq!(price, 1930);
     }
 
+    #[test]
+    fn test_sides() {
+        let graph = SMALL_EXAMPLE.parse::<Map>().unwrap().graph();
+        let sides = |id| graph.components()[&id].iter().map(Region::sides).collect::<Vec<_>>();
+        assert_eq!(sides('A'), [4]);
+        assert_eq!(sides('B'), [4]);
+        assert_eq!(sides('C'), [8]);
+        assert_eq!(sides('D'), [4]);
+        assert_eq!(sides('E'), [4]);
+    }
+
+    #[test]
+    fn test_part2() {
+        use crate::core::Solution;
+
+        assert_eq!(Part2::solve(LARGER_EXAMPLE), 1206);
+        assert_eq!(Part2::solve("AAAAAA\nAAABBA\nAAABBA\nABBAAA\nABBAAA\nAAAAAA"), 368);
+    }
+
+    #[test]
+    fn test_labelling_backends_agree() {
+        let map = LARGER_EXAMPLE.parse::<Map>().unwrap();
+        let (dfs, union_find) = (map.graph_with(Labelling::Dfs), map.graph_with(Labelling::UnionFind));
+
+        assert_eq!(dfs.labels(), union_find.labels());
+        assert_eq!(dfs.len(), 11);
+        for label in 0..dfs.len() as Label {
+            let (lhs, rhs) = (dfs.region(label), union_find.region(label));
+            assert_eq!((lhs.id, lhs.area(), lhs.perimeter(), lhs.sides()), (rhs.id, rhs.area(), rhs.perimeter(), rhs.sides()));
+        }
+
+        // the C plot at (7, 4) is cut off from the big C region
+        assert_eq!(union_find.region_at((7, 4).into()).area(), 1);
+        assert_eq!(union_find.region_at((4, 4).into()).area(), 14);
+        assert_ne!(union_find.label((7, 4).into()), union_find.label((4, 4).into()));
+    }
+
+    #[test]
+    fn test_ragged_map() {
+        let error = "AAAA\nBBC\nBBCC".parse::<Map>().unwrap_err();
+        assert_eq!(error.to_string(), "line 2: expected 4 cells like the first line, found 3");
+        assert!(<Part1 as crate::core::Solution<12, 1>>::try_solve("AB\nABC").is_err());
+
+        let wide = format!("{}B", "A".repeat(70_000));
+        assert_eq!(wide.parse::<Map>().unwrap_err().to_string(), "line 1: 70001 cells, more than 65536");
+    }
+
+    #[test]
+    fn test_multibyte_cells() {
+        let map = "ééa\néaa\n🌱🌱a".parse::<Map>().unwrap();
+        assert_eq!((map.width(), map.height()), (3, 3));
+        let graph = map.graph();
+        assert_eq!(graph.region_at((0, 0).into()).area(), 3);
+        assert_eq!(graph.region_at((2, 0).into()).area(), 4);
+        assert_eq!(graph.components()[&'🌱'][0].sides(), 4);
+    }
+
+    #[test]
+    fn test_token_cells() {
+        let input = "oak oak elm\nelm oak elm\nelm elm elm";
+        let map = Map::from_rows(input.lines().map(str::split_whitespace)).unwrap();
+        let graph = map.graph();
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph.components()[&"oak"][0].area(), 3);
+        assert_eq!(graph.components()[&"elm"][0].perimeter(), 14);
+        assert_eq!(map.navigator().passable(|from, to| from == to).bfs((2, 0).into(), (0, 1).into()).unwrap().cost, 5);
+
+        assert!(Map::from_rows(["oak elm", "oak"].map(str::split_whitespace)).is_err());
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let graph = "OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO".parse::<Map>().unwrap().graph();
+        let outer = graph.label((0, 0).into());
+        let inner = [(1, 1), (3, 1), (1, 3), (3, 3)].map(|coords: (u16, u16)| graph.label(coords.into()));
+
+        let adjacency = graph.adjacency();
+        assert_eq!(adjacency.neighbors(outer).collect::<Vec<_>>(), inner.map(|label| (label, 4)));
+        assert_eq!(adjacency.shared_edge(inner[0], outer), 4);
+        assert_eq!(adjacency.shared_edge(inner[0], inner[1]), 0);
+
+        assert_eq!(graph.enclosed().collect::<Vec<_>>(), inner.map(|label| (label, outer)));
+        assert_eq!(graph.enclosing(inner[2]), Some(outer));
+        assert_eq!(graph.enclosing(outer), None);
+
+        assert_eq!(graph.border_edges().count(), 36 + 4 * 4);
+        assert_eq!(graph.border_edges().filter(|edge| edge.outside.is_none()).count(), 20);
+        assert!(graph.border_edges().all(|edge| edge.inside == graph.label(edge.coords)));
+    }
+
+    #[test]
+    fn test_adjacency_without_enclosures() {
+        let graph = SMALL_EXAMPLE.parse::<Map>().unwrap().graph();
+        let (c, d) = (graph.label((2, 1).into()), graph.label((3, 1).into()));
+        let adjacency = graph.adjacency();
+        assert_eq!(adjacency.shared_edge(c, d), 2);
+        assert_eq!(adjacency.shared_edge(d, c), 2);
+        assert_eq!(adjacency.neighbors(d).count(), 2);
+        assert_eq!(graph.enclosed().count(), 0);
+    }
+
+    #[test]
+    fn test_enclosed_with_holes() {
+        let graph = "AAAAA\nABBBA\nABCBA\nABBBA\nAAAAA".parse::<Map>().unwrap().graph();
+        let [a, b, c] = [(0, 0), (1, 1), (2, 2)].map(|coords: (u16, u16)| graph.label(coords.into()));
+        assert_eq!(graph.enclosed().collect::<Vec<_>>(), [(b, a), (c, b)]);
+
+        // B borders D on the outside, so only the region in its hole is enclosed
+        let graph = "AAAAAA\nABBBDA\nABCBDA\nABBBDA\nAAAAAA".parse::<Map>().unwrap().graph();
+        let [b, c, d] = [(1, 1), (2, 2), (4, 1)].map(|coords: (u16, u16)| graph.label(coords.into()));
+        assert_eq!(graph.enclosed().collect::<Vec<_>>(), [(c, b)]);
+        assert_eq!(graph.enclosing(b), None);
+        assert_eq!(graph.enclosing(d), None);
+    }
+
+    #[test]
+    fn test_bounding_box_and_centroid() {
+        let graph = "OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO".parse::<Map>().unwrap().graph();
+        let outer = graph.region_at((0, 0).into());
+        assert_eq!(outer.bounding_box(), BoundingBox { min: (0, 0).into(), max: (4, 4).into() });
+        assert_eq!(outer.centroid(), (2.0, 2.0));
+        let inner = graph.region_at((3, 1).into());
+        assert_eq!(inner.bounding_box().width(), 1);
+        assert_eq!(inner.centroid(), (3.0, 1.0));
+
+        let graph = SMALL_EXAMPLE.parse::<Map>().unwrap().graph();
+        let c = graph.region_at((2, 1).into()).bounding_box();
+        assert_eq!((c.min, c.width(), c.height()), ((2, 1).into(), 2, 3));
+        assert!(c.contains((2, 3).into()));
+    }
+
+    #[test]
+    fn test_navigator_within_region() {
+        let map = SMALL_EXAMPLE.parse::<Map>().unwrap();
+        let navigator = map.navigator().passable(|from, to| from == to);
+
+        let path = navigator.bfs((2, 1).into(), (3, 3).into()).unwrap();
+        assert_eq!(path.cost, 3);
+        assert_eq!(path.plots, [(2, 1), (2, 2), (3, 2), (3, 3)].map(Coords::from));
+        assert_eq!(navigator.dijkstra((2, 1).into(), (3, 3).into()).unwrap(), path);
+        assert_eq!(navigator.a_star((2, 1).into(), (3, 3).into()).unwrap(), path);
+        assert_eq!(navigator.bfs((0, 3).into(), (0, 3).into()).unwrap().plots, [Coords::from((0, 3))]);
+
+        assert!(navigator.bfs((0, 0).into(), (0, 1).into()).is_none());
+        assert!(navigator.dijkstra((0, 0).into(), (0, 1).into()).is_none());
+        assert!(navigator.a_star((0, 0).into(), (0, 1).into()).is_none());
+    }
+
+    #[test]
+    fn test_navigator_costs() {
+        let grid = "131\n191\n111".parse::<Vec2d<char>>().unwrap().map(|cell| cell.to_digit(10).unwrap() as u64);
+        let navigator = MapNavigator::new(&grid).cost(|_, to| *to);
+        let (start, goal) = ((0, 0).into(), (2, 2).into());
+
+        let cheapest = navigator.dijkstra(start, goal).unwrap();
+        assert_eq!(cheapest.cost, 4);
+        assert_eq!(cheapest.plots, [(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)].map(Coords::from));
+        assert_eq!(navigator.a_star(start, goal).unwrap().cost, 4);
+        assert_eq!(navigator.bfs(start, goal).unwrap().cost, 4);
+
+        let walled = MapNavigator::new(&grid).cost(|_, to| *to).passable(|_, to| *to != 1);
+        assert!(walled.dijkstra(start, goal).is_none());
+    }
+
+    #[test]
+    fn test_navigator_distances() {
+        let grid = "...\n.#.\n...".parse::<Vec2d<char>>().unwrap();
+        let navigator = MapNavigator::new(&grid).passable(|_, to| *to != '#');
+        let distances = navigator.distances([(0, 0).into(), (2, 2).into()]);
+        assert_eq!(
+            distances.map(|distance| distance.map_or('#', |distance| char::from_digit(distance, 10).unwrap())).to_string(),
+            "012\n1#1\n210",
+        );
+        assert_eq!(navigator.distances([]).iter().flatten().count(), 0);
+    }
+
+    #[test]
+    fn test_render() {
+        colored::control::set_override(false);
+        let graph = SMALL_EXAMPLE.parse::<Map>().unwrap().graph();
+
+        assert_eq!(graph.render(RenderOptions::default()), format!("{SMALL_EXAMPLE}\n"));
+        assert_eq!(graph.render(RenderOptions { fences: true, legend: false }), concat!(
+            "+-+-+-+-+\n",
+            "|A A A A|\n",
+            "+-+-+-+-+\n",
+            "|B B|C|D|\n",
+            "+   + +-+\n",
+            "|B B|C C|\n",
+            "+-+-+-+ +\n",
+            "|E E E|C|\n",
+            "+-+-+-+-+\n",
+        ));
+        let legend = graph.render(RenderOptions { fences: false, legend: true });
+        assert!(legend.contains("#2 C: area 4, perimeter 10, price 40, sides 8, discounted price 32\n"), "{legend}");
+        assert_eq!(legend.lines().count(), 4 + 5);
+    }
+
+    #[test]
+    fn test_region_colors() {
+        let colors = (0..64).map(region_color).collect::<HashSet<_>>();
+        assert_eq!(colors.len(), 64);
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let graph = "AB\nAA".parse::<Map>().unwrap().graph();
+        let mut image = Vec::new();
+        graph.write_ppm(&mut image, 3, true).unwrap();
+
+        let header = b"P6\n6 6\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        let pixels = &image[header.len()..];
+        assert_eq!(pixels.len(), 6 * 6 * 3);
+        let pixel = |x: usize, y: usize| &pixels[(y * 6 + x) * 3..][..3];
+        let (a, b) = (region_color(0), region_color(1));
+        assert_eq!(pixel(1, 1), [a.0, a.1, a.2]);
+        assert_eq!(pixel(4, 1), [b.0, b.1, b.2]);
+        assert_eq!(pixel(0, 1), [0, 0, 0]);
+        assert_eq!(pixel(1, 5), [0, 0, 0]);
+        // no fence within a region
+        assert_eq!(pixel(1, 2), [a.0, a.1, a.2]);
+    }
+
+    /// Regions by label with their plots in row-major order, to compare graphs built by different backends.
+    fn regions(graph: &MapGraph) -> Vec<(char, Vec<Coords>, usize)> {
+        (0..graph.len() as Label)
+            .map(|label| graph.region(label))
+            .map(|region| {
+                let mut plots = region.plots().to_vec();
+                plots.sort_by_key(|&Coords { x, y }| (y, x));
+                (region.id, plots, region.perimeter())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tiled_labelling() {
+        let map = SMALL_EXAMPLE.parse::<Map>().unwrap();
+        let sequential = map.graph();
+        for strips in [0, 1, 2, 3, 4, 9] {
+            let tiled = map.graph_with(Labelling::Tiled { strips });
+            assert_eq!(tiled.labels(), sequential.labels(), "{strips} strips");
+            assert_eq!(regions(&tiled), regions(&sequential), "{strips} strips");
+        }
+
+        // a U spanning both strips is only joined up at the bottom
+        let map = "ABA\nABA\nAAA".parse::<Map>().unwrap();
+        assert_eq!(map.graph_with(Labelling::Tiled { strips: 3 }).len(), 2);
+    }
+
+    #[test]
+    fn test_random_map() {
+        let map = Map::random(30, 20, 4, 7);
+        assert_eq!((map.width(), map.height()), (30, 20));
+        assert!(map.data.iter().all(|cell| ('A'..='D').contains(cell)));
+        assert_eq!(Map::random(30, 20, 4, 7).data, map.data);
+        assert_ne!(Map::random(30, 20, 4, 8).data, map.data);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_tiled_matches_sequential(
+            width in 1..40usize,
+            height in 1..40usize,
+            kinds in 1..5u8,
+            strips in 1..8usize,
+            seed in proptest::num::u64::ANY,
+        ) {
+            let map = Map::random(width, height, kinds, seed);
+            let (sequential, tiled) = (map.graph(), map.graph_with(Labelling::Tiled { strips }));
+            proptest::prop_assert_eq!(tiled.labels(), sequential.labels());
+            proptest::prop_assert_eq!(regions(&tiled), regions(&sequential));
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_labelling() {
+        let input = std::fs::read_to_string("inputs/input-12.txt").unwrap();
+        let map = input.parse::<Map>().unwrap();
+
+        let dfs = crate::core::bench::measure(20, || map.graph_with(Labelling::Dfs));
+        let union_find = crate::core::bench::measure(20, || map.graph_with(Labelling::UnionFind));
+        println!("day 12 over {}x{} plots: dfs {dfs:?}, union-find {union_find:?}", map.width(), map.height());
+        assert_eq!(map.graph_with(Labelling::Dfs).labels(), map.graph_with(Labelling::UnionFind).labels());
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_tiled_labelling() {
+        let map = Map::random(3000, 3000, 6, 12);
+        let strips = std::thread::available_parallelism().map_or(4, usize::from);
+
+        let dfs = crate::core::bench::measure(3, || map.graph_with(Labelling::Dfs));
+        let union_find = crate::core::bench::measure(3, || map.graph_with(Labelling::UnionFind));
+        let tiled = crate::core::bench::measure(3, || map.graph_with(Labelling::Tiled { strips }));
+        println!(
+            "day 12 over {}x{} random plots: dfs {dfs:?}, union-find {union_find:?}, tiled ({strips} strips) {tiled:?}",
+            map.width(), map.height(),
+        );
+        assert_eq!(map.graph().labels(), map.graph_with(Labelling::Tiled { strips }).labels());
+    }
+
     #[test]
     fn test_discounted() {
         let input = "EEEEE\nEXXXX\nEEEEE\nEXXXX\nEEEEE";
-        let discounted_price = 
+        let discounted_price = input.lines()
+            .collect::<Map>()
+            .graph()
+            .components()
+            .values()
+            .flatten()
+            .map(Region::discounted_price)
+            .sum::<usize>();
+        assert_eq!(discounted_price, 236);
     }
 }
\ No newline at end of file
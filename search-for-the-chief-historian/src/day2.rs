@@ -1,37 +1,75 @@
 
-use std::{cmp::Ordering, str::FromStr};
-use arrayvec::ArrayVec;
+use std::{cmp::Ordering, ops::RangeInclusive, str::FromStr};
+
+use crate::core::collections::SmallVec;
 
 pub type Level = u32;
 
-pub mod func {
-    pub fn pred_with_tolerance<T>(mut pred: impl FnMut(T) -> bool, mut tolerance: usize) -> impl FnMut(T) -> bool {
-        move |arg| (pred(arg) == false)
-            .then(|| if tolerance > 0 { tolerance -= 1; true } else { false })
-            .unwrap_or(true)
+/// How consecutive levels of a safe report must relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monotonicity {
+    /// Levels strictly increase or strictly decrease.
+    Strict,
+    /// Levels never decrease or never increase, equal neighbours are allowed.
+    NonStrict,
+}
+
+/// Rules a report has to follow to count as safe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafetyPolicy {
+    /// Allowed absolute difference between adjacent levels.
+    pub gap: RangeInclusive<Level>,
+    pub monotonicity: Monotonicity,
+    /// Number of levels that may be removed to make the report safe.
+    pub tolerance: usize,
+}
+
+impl SafetyPolicy {
+    pub const PART1: Self = Self {
+        gap: 1..=3,
+        monotonicity: Monotonicity::Strict,
+        tolerance: 0,
+    };
+
+    pub const PART2: Self = Self {
+        tolerance: 1,
+        ..Self::PART1
+    };
+
+    pub fn with_tolerance(self, tolerance: usize) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    /// Whether `next` may follow `prev` in a report heading in the `ordering` direction.
+    fn allows(&self, ordering: Ordering, [prev, next]: [Level; 2]) -> bool {
+        let direction = match self.monotonicity {
+            Monotonicity::Strict => prev.cmp(&next) == ordering,
+            Monotonicity::NonStrict => prev.cmp(&next) != ordering.reverse(),
+        };
+        direction && self.gap.contains(&prev.abs_diff(next))
     }
 }
 
+/// What to do with reports longer than `STACK_VEC_CAPACITY`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Move the levels to the heap.
+    #[default]
+    Spill,
+    /// Reject the report with a "too many levels" error.
+    Error,
+}
+
 #[derive(Debug)]
 pub struct Report<const STACK_VEC_CAPACITY: usize = 16> {
-    levels: ArrayVec<Level, STACK_VEC_CAPACITY>
+    levels: SmallVec<Level, STACK_VEC_CAPACITY>
 }
 
 impl<const STACK_VEC_CAPACITY: usize> Report<STACK_VEC_CAPACITY> {
-    const SAFETY_RANGE: std::ops::RangeInclusive<Level> = 1..=3;
-
     fn adjacent(&self) -> impl Iterator<Item = [Level; 2]> + '_ {
         self.levels.windows(2).map(|pair| pair.try_into().expect("windows are of size 2"))
     }
 
-    fn all_adjacent(&self, pred: impl FnMut([Level; 2]) -> bool) -> bool {
-        self.adjacent().all(pred)
-    }
- 
-    fn are_adjacent_in_range([prev, next]: [Level; 2]) -> bool {
-        Self::SAFETY_RANGE.contains(&prev.abs_diff(next))
-    }
-
     pub fn is_safe_by<const PART: usize>(&self) -> bool
     where
         Self: IsSafe<PART>
@@ -39,8 +77,138 @@ impl<const STACK_VEC_CAPACITY: usize> Report<STACK_VEC_CAPACITY> {
         self.is_safe()
     }
 
-    const fn monotonic_cmp(ordering: Ordering) -> impl FnMut([Level; 2]) -> bool {
-        move |[prev, next]| prev.cmp(&next) != ordering
+    /// Whether the report is safe under `policy`.
+    ///
+    /// Single pass in `O(n * policy.tolerance)`, allocation-free unless the report spilled to the heap.
+    /// See [`Self::tolerates`].
+    pub fn is_safe_under(&self, policy: &SafetyPolicy) -> bool {
+        self.levels.len() <= policy.tolerance.saturating_add(1)
+            || self.tolerates(policy, Ordering::Less)
+            || self.tolerates(policy, Ordering::Greater)
+    }
+
+    /// For every level track the fewest removals that leave a safe report ending at that level.
+    ///
+    /// A level can only follow one of the `tolerance + 1` levels before it, longer gaps would remove too much.
+    fn tolerates(&self, policy: &SafetyPolicy, ordering: Ordering) -> bool {
+        let levels = &self.levels;
+        let tolerance = policy.tolerance;
+        let mut removals = SmallVec::<usize, STACK_VEC_CAPACITY>::new();
+        for (index, &next) in levels.iter().enumerate() {
+            let fewest = (index.saturating_sub(tolerance.saturating_add(1))..index)
+                .filter(|&prev| policy.allows(ordering, [levels[prev], next]))
+                .map(|prev| removals[prev] + (index - prev - 1))
+                // or drop everything before
+                .fold(index, usize::min);
+            if fewest + (levels.len() - 1 - index) <= tolerance {
+                return true;
+            }
+            removals.push(fewest);
+        }
+        false
+    }
+}
+
+/// Rule broken by a pair of adjacent levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    GapTooLarge,
+    GapTooSmall,
+    ZeroGap,
+    DirectionChange,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Violation::GapTooLarge => "gap too large",
+            Violation::GapTooSmall => "gap too small",
+            Violation::ZeroGap => "zero gap",
+            Violation::DirectionChange => "direction change",
+        })
+    }
+}
+
+/// Why a report is unsafe, ignoring the policy's tolerance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    /// Index of the first level of the offending pair.
+    pub index: usize,
+    pub pair: [Level; 2],
+    pub violation: Violation,
+    /// Index of a level whose removal makes the report safe, if there is one.
+    pub fix: Option<usize>,
+}
+
+impl<const STACK_VEC_CAPACITY: usize> Report<STACK_VEC_CAPACITY> {
+    /// First adjacent pair breaking `policy`, or `None` if the report is safe without removing anything.
+    pub fn diagnose(&self, policy: &SafetyPolicy) -> Option<Diagnosis> {
+        let strict = policy.clone().with_tolerance(0);
+        let mut direction = None;
+        let (index, pair, violation) = self.adjacent()
+            .enumerate()
+            .find_map(|(index, pair @ [prev, next])| {
+                let gap = prev.abs_diff(next);
+                let violation = match prev.cmp(&next) {
+                    Ordering::Equal if policy.monotonicity == Monotonicity::Strict || !policy.gap.contains(&0) => Some(Violation::ZeroGap),
+                    Ordering::Equal => None,
+                    ordering if *direction.get_or_insert(ordering) != ordering => Some(Violation::DirectionChange),
+                    _ if gap > *policy.gap.end() => Some(Violation::GapTooLarge),
+                    _ if gap < *policy.gap.start() => Some(Violation::GapTooSmall),
+                    _ => None,
+                };
+                violation.map(|violation| (index, pair, violation))
+            })?;
+
+        let fix = (0..self.levels.len()).find(|&removed| self.levels.iter()
+            .enumerate()
+            .filter_map(|(index, level)| (index != removed).then_some(*level))
+            .collect::<Self>()
+            .is_safe_under(&strict)
+        );
+        Some(Diagnosis { index, pair, violation, fix })
+    }
+
+    /// Colour-highlighted explanation of [`Self::diagnose`].
+    pub fn explain<'a>(&'a self, policy: &SafetyPolicy) -> Explanation<'a, STACK_VEC_CAPACITY> {
+        Explanation {
+            report: self,
+            diagnosis: self.diagnose(policy),
+        }
+    }
+}
+
+pub struct Explanation<'a, const STACK_VEC_CAPACITY: usize> {
+    report: &'a Report<STACK_VEC_CAPACITY>,
+    diagnosis: Option<Diagnosis>,
+}
+
+impl<const STACK_VEC_CAPACITY: usize> std::fmt::Display for Explanation<'_, STACK_VEC_CAPACITY> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use colored::Colorize;
+
+        for (index, level) in self.report.levels.iter().enumerate() {
+            let level = level.to_string();
+            let level = match &self.diagnosis {
+                Some(diagnosis) if index == diagnosis.index || index == diagnosis.index + 1 => level.red().bold(),
+                Some(diagnosis) if diagnosis.fix == Some(index) => level.yellow(),
+                _ => level.normal(),
+            };
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{level}")?;
+        }
+
+        let Some(diagnosis) = &self.diagnosis else {
+            return write!(f, ": {}", "safe".green());
+        };
+        let [prev, next] = diagnosis.pair;
+        write!(f, ": {} between {prev} and {next} at index {}", diagnosis.violation, diagnosis.index)?;
+        match diagnosis.fix {
+            Some(fix) => write!(f, ", removing {} at index {fix} fixes it", self.report.levels[fix]),
+            None => write!(f, ", no single removal fixes it"),
+        }
     }
 }
 
@@ -49,115 +217,226 @@ impl<const STACK_VEC_CAPACITY: usize> FromIterator<Level> for Report<STACK_VEC_C
         Self {
             levels: iter
                 .into_iter()
-                .collect::<ArrayVec<_, STACK_VEC_CAPACITY>>()
+                .collect::<SmallVec<_, STACK_VEC_CAPACITY>>()
         }
     }
 }
 
+impl<const STACK_VEC_CAPACITY: usize> Report<STACK_VEC_CAPACITY> {
+    pub fn parse_with(line: &str, overflow: Overflow) -> eyre::Result<Self> {
+        let mut levels = SmallVec::new();
+        for (index, level) in line.split_whitespace().enumerate() {
+            if index == STACK_VEC_CAPACITY && overflow == Overflow::Error {
+                eyre::bail!("too many levels, at most {STACK_VEC_CAPACITY} allowed: {line:?}");
+            }
+            levels.push(level.parse()?);
+        }
+        Ok(Self { levels })
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        self.levels.is_spilled()
+    }
+}
+
 impl<const STACK_VEC_CAPACITY: usize> FromStr for Report<STACK_VEC_CAPACITY> {
     type Err = eyre::Report;
 
+    /// Parses with [`Overflow::default`], long reports spill to the heap.
     fn from_str(line: &str) -> eyre::Result<Self> {
-        line.split_whitespace()
-            .take(STACK_VEC_CAPACITY)
-            .map(str::parse)
-            .collect::<Result<Self, _>>()
-            .map_err(Into::into)
+        Self::parse_with(line, Overflow::default())
     }
 }
 
+pub struct Part1;
+
+pub struct Part2;
+
 pub trait IsSafe<const PART: usize> {
     fn is_safe(&self) -> bool;
 }
 
 pub mod part1 {
     use super::*;
-    use crate::Day;
+    use crate::core;
 
     impl<const STACK_VEC_CAPACITY: usize> super::IsSafe<1> for Report<STACK_VEC_CAPACITY> {
         fn is_safe(&self) -> bool {
-            self.all_adjacent(|levels| Self::are_adjacent_in_range(levels))
-            && (
-                self.all_adjacent(Self::monotonic_cmp(Ordering::Less))
-                || self.all_adjacent(Self::monotonic_cmp(Ordering::Greater))
-            )
+            self.is_safe_under(&SafetyPolicy::PART1)
         }
     }
 
-    pub fn solve() {
-        let day = Day::new(2, 1);
+    impl core::Solution<2, 1> for super::Part1 {
+        type Result = usize;
 
-        let result = day.input()
-            .lines()
-            .map(|line| line
-                .parse::<Report>()
-                .unwrap()
-            )
-            .filter(Report::is_safe_by::<1>)
-            .count();
-        day.report(result);
+        fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+            Ok(input
+                .lines()
+                .map(str::parse::<Report>)
+                .collect::<eyre::Result<Vec<_>>>()?
+                .iter()
+                .filter(|report| report.is_safe_by::<1>())
+                .count())
+        }
     }
 }
 
 pub mod part2 {
     use super::*;
-    use crate::Day;
+    use crate::core;
 
     impl<const STACK_VEC_CAPACITY: usize> super::IsSafe<2> for Report<STACK_VEC_CAPACITY> {
         fn is_safe(&self) -> bool {
-            (0..self.levels.len())
-                .into_iter()
-                .map(|selector| self.levels.iter()
-                    .map(Clone::clone)
-                    .enumerate()
-                    .filter_map(|(index, levels)| if index != selector {
-                        Some(levels)
-                    } else {
-                        None
-                    })
-                    .collect::<Self>()
-                )
-                .any(|rep| rep.is_safe_by::<1>())
+            self.is_safe_under(&SafetyPolicy::PART2)
         }
     }
 
-    pub fn solve() {
-        let day = Day::new(2, 2);
+    impl core::Solution<2, 2> for super::Part2 {
+        type Result = usize;
 
-        let result = day.input()
-            .lines()
-            .map(|line| line
-                .parse::<Report>()
-                .unwrap()
-            )
-            .filter(Report::is_safe_by::<2>)
-            .count();
-        day.report(result);
+        fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+            Ok(input
+                .lines()
+                .map(str::parse::<Report>)
+                .collect::<eyre::Result<Vec<_>>>()?
+                .iter()
+                .filter(|report| report.is_safe_by::<2>())
+                .count())
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::Solution;
+
+    const EXAMPLE: &str = "7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9\n";
+
+    #[test]
+    fn test_part1() { assert_eq!(Part1::solve(EXAMPLE), 2) }
+
+    #[test]
+    fn test_part2() { assert_eq!(Part2::solve(EXAMPLE), 4) }
 
     #[test]
-    fn test_1() { assert_eq!("7 6 4 2 1".parse::<Report>().unwrap().is_safe_by::<2>(), true) }
+    fn test_1() { assert!("7 6 4 2 1".parse::<Report>().unwrap().is_safe_by::<2>()) }
     
     #[test]
-    fn test_2() { assert_eq!("1 2 7 8 9".parse::<Report>().unwrap().is_safe_by::<2>(), false) }
+    fn test_2() { assert!(!"1 2 7 8 9".parse::<Report>().unwrap().is_safe_by::<2>()) }
     
     #[test]
-    fn test_3() { assert_eq!("9 7 6 2 1".parse::<Report>().unwrap().is_safe_by::<2>(), false) }
+    fn test_3() { assert!(!"9 7 6 2 1".parse::<Report>().unwrap().is_safe_by::<2>()) }
     
     #[test]
-    fn test_4() { assert_eq!("1 3 2 4 5".parse::<Report>().unwrap().is_safe_by::<2>(), true) }
+    fn test_4() { assert!("1 3 2 4 5".parse::<Report>().unwrap().is_safe_by::<2>()) }
     
     #[test]
-    fn test_5() { assert_eq!("8 6 4 4 1".parse::<Report>().unwrap().is_safe_by::<2>(), true) }
+    fn test_5() { assert!("8 6 4 4 1".parse::<Report>().unwrap().is_safe_by::<2>()) }
     
     #[test]
-    fn test_6() { assert_eq!("1 3 6 7 9".parse::<Report>().unwrap().is_safe_by::<2>(), true) }
+    fn test_6() { assert!("1 3 6 7 9".parse::<Report>().unwrap().is_safe_by::<2>()) }
     
     #[test]
-    fn test_7() { assert_eq!("1 5 6 7 9".parse::<Report>().unwrap().is_safe_by::<2>(), true) }
-}
\ No newline at end of file
+    fn test_7() { assert!("1 5 6 7 9".parse::<Report>().unwrap().is_safe_by::<2>()) }
+
+    #[test]
+    fn test_overflow() {
+        let line = "1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20";
+
+        let spilled = Report::<4>::parse_with(line, Overflow::Spill).unwrap();
+        assert!(spilled.is_spilled());
+        assert!(spilled.is_safe_under(&SafetyPolicy::PART1));
+        assert!(!Report::<4>::parse_with(&format!("{line} 30"), Overflow::Spill).unwrap().is_safe_under(&SafetyPolicy::PART1));
+        assert!(Report::<4>::parse_with(&format!("{line} 30"), Overflow::Spill).unwrap().is_safe_under(&SafetyPolicy::PART2));
+
+        let err = Report::<4>::parse_with(line, Overflow::Error).unwrap_err();
+        assert!(err.to_string().contains("too many levels"));
+        assert!(!Report::<20>::parse_with(line, Overflow::Error).unwrap().is_spilled());
+    }
+
+    /// Reference implementation: check every adjacent pair, then try every way of removing up to `tolerance` levels.
+    fn is_safe_brute_force(report: &Report, policy: &SafetyPolicy) -> bool {
+        let directed = |ordering| report.adjacent().all(|pair| policy.allows(ordering, pair));
+        directed(Ordering::Less) || directed(Ordering::Greater) || policy.tolerance > 0 && (0..report.levels.len()).any(|selector| {
+            let removed = report.levels.iter()
+                .enumerate()
+                .filter_map(|(index, level)| (index != selector).then_some(*level))
+                .collect::<Report>();
+            is_safe_brute_force(&removed, &policy.clone().with_tolerance(policy.tolerance - 1))
+        })
+    }
+
+    #[test]
+    fn test_non_strict_policy() {
+        let report = "1 2 2 5".parse::<Report>().unwrap();
+        let policy = SafetyPolicy { gap: 0..=3, monotonicity: Monotonicity::NonStrict, tolerance: 0 };
+        assert!(report.is_safe_under(&policy));
+        assert!(!report.is_safe_under(&SafetyPolicy { monotonicity: Monotonicity::Strict, ..policy }));
+        assert!(report.is_safe_under(&SafetyPolicy::PART2));
+        assert!(!report.is_safe_under(&SafetyPolicy::PART1));
+    }
+
+    #[test]
+    fn test_unbounded_tolerance() {
+        let policy = SafetyPolicy::PART1.with_tolerance(usize::MAX);
+        let report = "1 2 3".parse::<Report>().unwrap();
+        assert!(report.is_safe_under(&policy));
+        assert!(report.tolerates(&policy, Ordering::Greater));
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let diagnose = |line: &str| line.parse::<Report>().unwrap().diagnose(&SafetyPolicy::PART2);
+        let diagnosis = |index, pair, violation, fix| Some(Diagnosis { index, pair, violation, fix });
+
+        assert_eq!(diagnose("7 6 4 2 1"), None);
+        assert_eq!(diagnose("1 2 7 8 9"), diagnosis(1, [2, 7], Violation::GapTooLarge, None));
+        assert_eq!(diagnose("9 7 6 2 1"), diagnosis(2, [6, 2], Violation::GapTooLarge, None));
+        assert_eq!(diagnose("1 3 2 4 5"), diagnosis(1, [3, 2], Violation::DirectionChange, Some(1)));
+        assert_eq!(diagnose("8 6 4 4 1"), diagnosis(2, [4, 4], Violation::ZeroGap, Some(2)));
+        assert_eq!(diagnose("1 2 2 6"), diagnosis(1, [2, 2], Violation::ZeroGap, None));
+        assert_eq!(
+            "1 4 5".parse::<Report>().unwrap().diagnose(&SafetyPolicy { gap: 2..=3, ..SafetyPolicy::PART1 }),
+            diagnosis(1, [4, 5], Violation::GapTooSmall, Some(2)),
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        colored::control::set_override(false);
+        let report = "1 3 2 4 5".parse::<Report>().unwrap();
+        assert_eq!(
+            report.explain(&SafetyPolicy::PART2).to_string(),
+            "1 3 2 4 5: direction change between 3 and 2 at index 1, removing 3 at index 1 fixes it",
+        );
+        assert_eq!("7 6 4 2 1".parse::<Report>().unwrap().explain(&SafetyPolicy::PART1).to_string(), "7 6 4 2 1: safe");
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_policy_matches_brute_force(
+            levels in proptest::collection::vec(1..12 as Level, 0..=10),
+            low in 0..3 as Level,
+            width in 0..3 as Level,
+            strict in proptest::bool::ANY,
+            tolerance in 0..=3usize,
+        ) {
+            let report = levels.into_iter().collect::<Report>();
+            let policy = SafetyPolicy {
+                gap: low..=low + width,
+                monotonicity: if strict { Monotonicity::Strict } else { Monotonicity::NonStrict },
+                tolerance,
+            };
+            proptest::prop_assert_eq!(report.is_safe_under(&policy), is_safe_brute_force(&report, &policy));
+        }
+
+        #[test]
+        fn test_diagnosis_matches_safety(levels in proptest::collection::vec(1..12 as Level, 0..=10)) {
+            let report = levels.into_iter().collect::<Report>();
+            let diagnosis = report.diagnose(&SafetyPolicy::PART2);
+            proptest::prop_assert_eq!(diagnosis.is_none(), report.is_safe_under(&SafetyPolicy::PART1));
+            proptest::prop_assert_eq!(diagnosis.is_none_or(|diagnosis| diagnosis.fix.is_some()), report.is_safe_under(&SafetyPolicy::PART2));
+        }
+    }
+}
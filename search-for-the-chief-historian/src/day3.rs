@@ -1,6 +1,5 @@
 
-use crate::Day;
-use regex::Regex;
+use crate::core;
 
 type Int = u64;
 
@@ -9,15 +8,21 @@ pub mod runtime { }
 pub mod tokens {
     use super::*;
     
-    use logos::{Lexer, Logos, Span};
+    use std::rc::Rc;
+    use logos::{Logos, Span};
+    use ast::{Expr, Stmt, Value};
+    use diagnostics::Diagnostic;
+    use evaluator::Mode;
 
-    #[derive(Debug, Logos)]
+    #[derive(Clone, Debug, PartialEq, Eq, Logos)]
     pub enum Token {
         // keywords
         #[token("do")]
         KwDo,
         #[token("don't")]
         KwDont,
+        #[token("mul")]
+        KwMul,
 
         // punctuation
         #[token("(")]
@@ -31,19 +36,111 @@ pub mod tokens {
         #[regex(r"\d{1,3}", |token| token.slice().parse::<Int>().unwrap())]
         Int(Int),
     }
+
+    /// Tokens with their spans, corrupted bytes come out as `Err(())`.
+    pub fn lex(program: &str) -> impl Iterator<Item = (Result<Token, ()>, Span)> + '_ {
+        Token::lexer(program).spanned()
+    }
+
+    /// Every valid statement of a corrupted program with its span, in source order.
+    ///
+    /// A statement is a run of adjacent tokens, anything else in between lexes to errors and so
+    /// breaks it up. On a mismatch parsing resumes at the token after the one it started at.
+    pub fn parse(program: &str) -> impl Iterator<Item = (Span, Stmt)> {
+        use Token::*;
+
+        let tokens = lex(program).collect::<Vec<_>>();
+        let mut position = 0;
+        std::iter::from_fn(move || {
+            while let Some(rest) = tokens.get(position..).filter(|rest| !rest.is_empty()) {
+                let (stmt, length) = match rest {
+                    [(Ok(KwDo), _), (Ok(LeftParenthesis), _), (Ok(RightParenthesis), _), ..] => {
+                        (Stmt::SetMode(Mode::Eval), 3)
+                    },
+                    [(Ok(KwDont), _), (Ok(LeftParenthesis), _), (Ok(RightParenthesis), _), ..] => {
+                        (Stmt::SetMode(Mode::Ignore), 3)
+                    },
+                    [
+                        (Ok(KwMul), _), (Ok(LeftParenthesis), _), (Ok(Int(lhs)), _),
+                        (Ok(Comma), _), (Ok(Int(rhs)), _), (Ok(RightParenthesis), _), ..
+                    ] => {
+                        (Stmt::Evaluate(Expr::Mul {
+                            lhs: Expr::Literal(Value::Int(*lhs)).into(),
+                            rhs: Expr::Literal(Value::Int(*rhs)).into(),
+                        }), 6)
+                    },
+                    _ => {
+                        position += 1;
+                        continue;
+                    },
+                };
+                let span = rest[0].1.start..rest[length - 1].1.end;
+                position += length;
+                return Some((span, stmt));
+            }
+            None
+        })
+    }
+
+    /// The single statement making up all of `program`, or where its tokens stop making sense.
+    pub fn parse_exact(program: &str) -> Result<Stmt, Diagnostic> {
+        use Token::*;
+
+        let mut tokens = lex(program);
+        let mut expect = |expected: &'static str, accepts: fn(&Token) -> bool| match tokens.next() {
+            Some((Ok(token), _)) if accepts(&token) => Ok(token),
+            Some((_, span)) => Err(Diagnostic::new(program, span.start, [expected])),
+            None => Err(Diagnostic::new(program, program.len(), [expected])),
+        };
+        let literal = |token| match token {
+            Int(number) => Rc::new(Expr::Literal(Value::Int(number))),
+            _ => unreachable!(),
+        };
+
+        let stmt = match expect("`do`, `don't` or `mul`", |token| matches!(token, KwDo | KwDont | KwMul))? {
+            keyword @ (KwDo | KwDont) => {
+                expect("`(`", |token| *token == LeftParenthesis)?;
+                expect("`)`", |token| *token == RightParenthesis)?;
+                Stmt::SetMode(if keyword == KwDo { Mode::Eval } else { Mode::Ignore })
+            },
+            _ => {
+                expect("`(`", |token| *token == LeftParenthesis)?;
+                let lhs = literal(expect("a literal", |token| matches!(token, Int(_)))?);
+                expect("`,`", |token| *token == Comma)?;
+                let rhs = literal(expect("a literal", |token| matches!(token, Int(_)))?);
+                expect("`)`", |token| *token == RightParenthesis)?;
+                Stmt::Evaluate(Expr::Mul { lhs, rhs })
+            },
+        };
+        match tokens.next() {
+            Some((_, span)) => Err(Diagnostic::new(program, span.start, ["end of input"])),
+            None => Ok(stmt),
+        }
+    }
 }
 
 pub mod ast {
-    use std::{iter::Sum, str::FromStr};
-    use evaluator::{Mode, TryMul};
+    use std::str::FromStr;
+    use super::evaluator::{self, Mode, TryAdd, TryDiv, TryMul, TrySub};
     use std::rc::Rc;
+    use std::num::ParseIntError;
     use nom::{
-        branch::alt, bytes::complete::tag, character::complete::digit1, combinator::map, sequence::tuple, IResult
+        branch::alt, bytes::complete::{tag, take_while_m_n}, combinator::{map, map_res},
+        error::{context, ContextError, ErrorKind, FromExternalError}, sequence::tuple, IResult
     };
 
     use super::*;
 
-    #[derive(Clone, Debug)]
+    /// Errors the parsers can report, [`nom::error::Error`] is the cheap one and
+    /// [`diagnostics::Expected`] the one to build a [`diagnostics::Diagnostic`] from.
+    pub trait ParseError<'a>:
+        nom::error::ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, ParseIntError> {}
+
+    impl<'a, E> ParseError<'a> for E
+    where
+        E: nom::error::ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, ParseIntError> {}
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
     pub enum Value {
         Int(Int),
         Void,
@@ -58,18 +155,87 @@ pub mod ast {
         }
     }
 
-    impl Sum for Value {
-        fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-            Value::Int(iter.filter_map(
-                |value| match value {
-                    Value::Int(number) => Some(number),
-                    Value::Void => None,
-                })
-                .sum()
-            )
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Op {
+        Add,
+        Sub,
+        Mul,
+        Div,
+    }
+
+    impl Op {
+        pub const ALL: [Op; 4] = [Op::Add, Op::Sub, Op::Mul, Op::Div];
+
+        pub const fn name(self) -> &'static str {
+            match self {
+                Op::Add => "add",
+                Op::Sub => "sub",
+                Op::Mul => "mul",
+                Op::Div => "div",
+            }
+        }
+
+        /// The name as quoted in diagnostics.
+        pub const fn quoted(self) -> &'static str {
+            match self {
+                Op::Add => "`add`",
+                Op::Sub => "`sub`",
+                Op::Mul => "`mul`",
+                Op::Div => "`div`",
+            }
+        }
+
+        fn call(self, lhs: Expr, rhs: Expr) -> Expr {
+            let (lhs, rhs) = (Rc::new(lhs), Rc::new(rhs));
+            match self {
+                Op::Add => Expr::Add { lhs, rhs },
+                Op::Sub => Expr::Sub { lhs, rhs },
+                Op::Mul => Expr::Mul { lhs, rhs },
+                Op::Div => Expr::Div { lhs, rhs },
+            }
         }
     }
 
+    impl std::fmt::Display for Op {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.name())
+        }
+    }
+
+    /// Dialect of the expression language accepted by the parsers.
+    #[derive(Clone, Debug)]
+    pub struct Grammar {
+        /// Most digits a literal may have.
+        pub literal_width: usize,
+        /// Most calls nested in each other, arguments deeper than that must be literals.
+        ///
+        /// One keeps call arguments literals, zero allows no calls at all.
+        pub max_depth: usize,
+        pub operators: &'static [Op],
+    }
+
+    impl Grammar {
+        /// The puzzle's dialect: `mul` of literals with up to three digits.
+        pub const PUZZLE: Self = Self {
+            literal_width: 3,
+            max_depth: 1,
+            operators: &[Op::Mul],
+        };
+
+        /// Every operator, calls nested up to 64 deep and any literal that fits into [`Int`].
+        pub const EXTENDED: Self = Self {
+            literal_width: 20,
+            max_depth: 64,
+            operators: &Op::ALL,
+        };
+
+        /// Whether a statement may start with `char`, the scanner skips ahead to those.
+        pub fn may_start(&self, char: char) -> bool {
+            char == 'd' || self.max_depth > 0 && self.operators.iter().any(|op| op.name().starts_with(char))
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
     pub enum Stmt {
         Evaluate(Expr),
         SetMode(evaluator::Mode),
@@ -77,10 +243,14 @@ pub mod ast {
 
     impl Stmt {
         pub fn parse(input: &str) -> IResult<&str, Self> {
+            Self::parse_with(&Grammar::PUZZLE, input)
+        }
+
+        pub fn parse_with<'a, E: ParseError<'a>>(grammar: &Grammar, input: &'a str) -> IResult<&'a str, Self, E> {
             let set_mode_parser = map(
                 alt((
-                    tag("do()"),
-                    tag("don't()")
+                    context("`do()`", tag("do()")),
+                    context("`don't()`", tag("don't()")),
                 )),
                 |tag| {
                     match tag {
@@ -91,7 +261,18 @@ pub mod ast {
                 }
             );
 
-            alt((set_mode_parser, map(Expr::parse, Stmt::Evaluate)))(input)
+            alt((set_mode_parser, map(|input| Expr::parse_call_with(grammar, input), Stmt::Evaluate)))(input)
+        }
+    }
+
+    /// Source form of the statement.
+    impl std::fmt::Display for Stmt {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Stmt::Evaluate(expr) => expr.fmt(f),
+                Stmt::SetMode(Mode::Eval) => write!(f, "do()"),
+                Stmt::SetMode(Mode::Ignore) => write!(f, "don't()"),
+            }
         }
     }
 
@@ -99,47 +280,94 @@ pub mod ast {
         type Err = eyre::Report;
 
         fn from_str(input: &str) -> Result<Self, Self::Err> {
-            match Self::parse(input) {
-                Ok((remaining, expr)) => {
-                    if remaining.trim().is_empty() {
-                        Ok(expr)
-                    } else {
-                        Err(eyre::eyre!("unexpected input remaining: {}", remaining))
-                    }
-                }
-                Err(err) => Err(eyre::eyre!("failed to parse expression: {:?}", err)),
-            }
+            Ok(diagnostics::parse_exact(input, |input| Self::parse_with(&Grammar::PUZZLE, input))?)
         }
     }
 
+    #[derive(Clone, Debug, PartialEq, Eq)]
     pub enum Expr {
         Literal(Value),
-        Mul{ lhs: Rc<Expr>, rhs: Rc<Expr> },
+        Add { lhs: Rc<Expr>, rhs: Rc<Expr> },
+        Sub { lhs: Rc<Expr>, rhs: Rc<Expr> },
+        Mul { lhs: Rc<Expr>, rhs: Rc<Expr> },
+        Div { lhs: Rc<Expr>, rhs: Rc<Expr> },
     }
 
     impl Expr {
+        /// Integer literal of one to three digits.
+        pub fn parse_literal(input: &str) -> IResult<&str, Self> {
+            Self::parse_literal_with(&Grammar::PUZZLE, input)
+        }
+
+        /// Multiplication of two literals, calls do not nest.
+        pub fn parse_call(input: &str) -> IResult<&str, Self> {
+            Self::parse_call_with(&Grammar::PUZZLE, input)
+        }
+
         pub fn parse(input: &str) -> IResult<&str, Self> {
-            let int_parser = map(digit1, |digits: &str| {
-                Expr::Literal(Value::Int(digits.parse::<Int>().unwrap()))
-            });
-    
-            // Parser for multiplication expressions
-            let mul_parser = map(
-                tuple((
-                    tag("mul("),
-                    Expr::parse,
-                    tag(","),
-                    Expr::parse,
-                    tag(")"),
-                )),
-                |(_, lhs, _, rhs, _)| Expr::Mul {
-                    lhs: Rc::new(lhs),
-                    rhs: Rc::new(rhs),
-                },
-            );
+            Self::parse_with(&Grammar::PUZZLE, input)
+        }
+
+        /// Integer literal of at most `grammar.literal_width` digits, literals overflowing [`Int`] are rejected.
+        pub fn parse_literal_with<'a, E: ParseError<'a>>(grammar: &Grammar, input: &'a str) -> IResult<&'a str, Self, E> {
+            context("a literal", map_res(take_while_m_n(1, grammar.literal_width, |char: char| char.is_ascii_digit()), |digits: &str| {
+                digits.parse::<Int>().map(|number| Expr::Literal(Value::Int(number)))
+            }))(input)
+        }
 
-            // Combine parsers to handle either integers or multiplication expressions
-            alt((mul_parser, int_parser))(input)
+        /// Call of one of `grammar.operators` with two arguments.
+        pub fn parse_call_with<'a, E: ParseError<'a>>(grammar: &Grammar, input: &'a str) -> IResult<&'a str, Self, E> {
+            Self::parse_call_at(grammar, 0, input)
+        }
+
+        pub fn parse_with<'a, E: ParseError<'a>>(grammar: &Grammar, input: &'a str) -> IResult<&'a str, Self, E> {
+            Self::parse_at(grammar, 0, input)
+        }
+
+        /// Call inside `depth` other calls.
+        fn parse_call_at<'a, E: ParseError<'a>>(grammar: &Grammar, depth: usize, input: &'a str) -> IResult<&'a str, Self, E> {
+            // no operator is expected past the deepest call
+            let operators = if depth < grammar.max_depth { grammar.operators } else { &[] };
+            let Some((input, op)) = operators
+                .iter()
+                .find_map(|&op| input.strip_prefix(op.name()).map(|input| (input, op)))
+            else {
+                let expected = operators
+                    .iter()
+                    .map(|op| E::add_context(input, op.quoted(), E::from_error_kind(input, ErrorKind::Tag)))
+                    .reduce(E::or)
+                    .unwrap_or_else(|| E::from_error_kind(input, ErrorKind::Tag));
+                return Err(nom::Err::Error(expected));
+            };
+
+            let argument = |input| Self::parse_at(grammar, depth + 1, input);
+
+            map(
+                tuple((context("`(`", tag("(")), argument, context("`,`", tag(",")), argument, context("`)`", tag(")")))),
+                move |(_, lhs, _, rhs, _)| op.call(lhs, rhs),
+            )(input)
+        }
+
+        fn parse_at<'a, E: ParseError<'a>>(grammar: &Grammar, depth: usize, input: &'a str) -> IResult<&'a str, Self, E> {
+            // Combine parsers to handle either integers or calls
+            alt((
+                |input| Expr::parse_call_at(grammar, depth, input),
+                |input| Expr::parse_literal_with(grammar, input),
+            ))(input)
+        }
+    }
+
+    /// Source form of the expression.
+    impl std::fmt::Display for Expr {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let (op, lhs, rhs) = match self {
+                Expr::Literal(value) => return value.fmt(f),
+                Expr::Add { lhs, rhs } => (Op::Add, lhs, rhs),
+                Expr::Sub { lhs, rhs } => (Op::Sub, lhs, rhs),
+                Expr::Mul { lhs, rhs } => (Op::Mul, lhs, rhs),
+                Expr::Div { lhs, rhs } => (Op::Div, lhs, rhs),
+            };
+            write!(f, "{op}({lhs},{rhs})")
         }
     }
 
@@ -147,72 +375,602 @@ pub mod ast {
         type Err = eyre::Report;
 
         fn from_str(input: &str) -> Result<Self, Self::Err> {
-            match Self::parse(input) {
-                Ok((remaining, expr)) => {
-                    if remaining.trim().is_empty() {
-                        Ok(expr)
-                    } else {
-                        Err(eyre::eyre!("unexpected input remaining: {}", remaining))
-                    }
-                }
-                Err(err) => Err(eyre::eyre!("failed to parse expression: {:?}", err)),
-            }
+            Ok(diagnostics::parse_exact(input, |input| Self::parse_with(&Grammar::PUZZLE, input))?)
+        }
+    }
+
+    impl Value {
+        /// Overflow-checked `op` on two integers.
+        fn checked(op: Op, lhs: &Self, rhs: &Self) -> eyre::Result<Self> {
+            let (Value::Int(lhs), Value::Int(rhs)) = (lhs, rhs) else {
+                eyre::bail!("{} is undefined for args: {:?}, {:?}", op, lhs, rhs);
+            };
+            let result = match op {
+                Op::Add => lhs.checked_add(*rhs),
+                Op::Sub => lhs.checked_sub(*rhs),
+                Op::Mul => lhs.checked_mul(*rhs),
+                Op::Div if *rhs == 0 => eyre::bail!("division by zero: div({}, {})", lhs, rhs),
+                Op::Div => lhs.checked_div(*rhs),
+            };
+            result
+                .map(Value::Int)
+                .ok_or_else(|| eyre::eyre!("{} overflows for args: {}, {}", op, lhs, rhs))
+        }
+    }
+
+    impl evaluator::TryAdd for Value {
+        fn try_add(&self, other: &Self) -> eyre::Result<Self> {
+            Value::checked(Op::Add, self, other)
+        }
+    }
+
+    impl evaluator::TrySub for Value {
+        fn try_sub(&self, other: &Self) -> eyre::Result<Self> {
+            Value::checked(Op::Sub, self, other)
         }
     }
 
     impl evaluator::TryMul for Value {
         fn try_mul(&self, other: &Self) -> eyre::Result<Self> {
-            match (self, other) {
-                (Value::Int(lhs), Value::Int(rhs)) => Ok(Value::Int(lhs * rhs)),
-                (lhs, rhs) => Err(eyre::eyre!("mul is undefined for args: {:?}, {:?}", lhs, rhs))
-            }
+            Value::checked(Op::Mul, self, other)
+        }
+    }
+
+    impl evaluator::TryDiv for Value {
+        fn try_div(&self, other: &Self) -> eyre::Result<Self> {
+            Value::checked(Op::Div, self, other)
         }
     }
 
     impl Expr {
         pub fn eval(&self) -> eyre::Result<Value> {
             match self {
+                Expr::Add { lhs, rhs } => lhs.eval()?.try_add(&rhs.eval()?),
+                Expr::Sub { lhs, rhs } => lhs.eval()?.try_sub(&rhs.eval()?),
                 Expr::Mul { lhs, rhs } => lhs.eval()?.try_mul(&rhs.eval()?),
+                Expr::Div { lhs, rhs } => lhs.eval()?.try_div(&rhs.eval()?),
                 Expr::Literal(value) => Ok(value.clone()),
             }
         }
     }
 }
 
-pub fn run(program: &str) -> ast::Value {
-    let regex = Regex::new(r"mul\(\d{1,3},\d{1,3}\)").unwrap();
-    let mut evaluator = evaluator::Evaluator::default();
+pub mod evaluator {
+    use super::ast::{Stmt, Value};
+
+    /// Whether `mul` statements are currently evaluated, toggled by `do()` and `don't()`.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub enum Mode {
+        #[default]
+        Eval,
+        Ignore,
+    }
 
-    regex.find_iter(program)
-        .map(|needle| needle.as_str()
-            .parse::<ast::Stmt>()
-            .expect("parsing successful")
-        )
-        .map(|expr| evaluator
-            .process(expr)
-            .expect("statement processing successful")
-        )
-        .sum::<ast::Value>()
+    pub trait TryAdd: Sized {
+        fn try_add(&self, other: &Self) -> eyre::Result<Self>;
+    }
+
+    pub trait TrySub: Sized {
+        fn try_sub(&self, other: &Self) -> eyre::Result<Self>;
+    }
+
+    pub trait TryMul: Sized {
+        fn try_mul(&self, other: &Self) -> eyre::Result<Self>;
+    }
+
+    pub trait TryDiv: Sized {
+        fn try_div(&self, other: &Self) -> eyre::Result<Self>;
+    }
+
+    impl std::fmt::Display for Mode {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.pad(match self {
+                Mode::Eval => "eval",
+                Mode::Ignore => "ignore",
+            })
+        }
+    }
+
+    /// A processed statement as recorded by a tracing [`Evaluator`].
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Step {
+        /// Byte offset into the program, unknown for statements fed to [`Evaluator::process`].
+        pub offset: Option<usize>,
+        pub stmt: Stmt,
+        pub before: Mode,
+        pub after: Mode,
+        pub value: Value,
+    }
+
+    impl Step {
+        /// Whether the statement was an expression left unevaluated by `don't()`.
+        pub fn skipped(&self) -> bool {
+            matches!(self.stmt, Stmt::Evaluate(_)) && self.before == Mode::Ignore
+        }
+    }
+
+    /// Every statement an [`Evaluator`] processed, printable as a table or dumped as JSON.
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct Trace {
+        steps: Vec<Step>,
+    }
+
+    impl Trace {
+        pub fn steps(&self) -> &[Step] {
+            &self.steps
+        }
+
+        /// JSON array with an object per step, unknown offsets and void values are `null`.
+        pub fn to_json(&self) -> String {
+            let steps = self.steps
+                .iter()
+                .map(|step| {
+                    let offset = step.offset.map_or("null".to_owned(), |offset| offset.to_string());
+                    let value = match step.value {
+                        Value::Int(number) => number.to_string(),
+                        Value::Void => "null".to_owned(),
+                    };
+                    // statements print without quotes or backslashes, no escaping needed
+                    format!(
+                        r#"{{"offset":{offset},"stmt":"{}","before":"{}","after":"{}","skipped":{},"value":{value}}}"#,
+                        step.stmt, step.before, step.after, step.skipped(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]", steps.join(","))
+        }
+    }
+
+    impl std::fmt::Display for Trace {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:>6}  {:<6}  {:<6}  {:<8}  stmt", "offset", "before", "after", "value")?;
+            for step in &self.steps {
+                let offset = step.offset.map_or("?".to_owned(), |offset| offset.to_string());
+                let value = if step.skipped() { "skipped".to_owned() } else { step.value.to_string() };
+                write!(f, "\n{offset:>6}  {:<6}  {:<6}  {value:<8}  {}", step.before, step.after, step.stmt)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Evaluator {
+        mode: Mode,
+        trace: Option<Trace>,
+    }
 
+    impl Evaluator {
+        /// Evaluator recording every processed statement into a [`Trace`].
+        pub fn traced() -> Self {
+            Self { trace: Some(Trace::default()), ..Self::default() }
+        }
+
+        pub fn mode(&self) -> Mode {
+            self.mode
+        }
+
+        pub fn trace(&self) -> Option<&Trace> {
+            self.trace.as_ref()
+        }
+
+        pub fn into_trace(self) -> Option<Trace> {
+            self.trace
+        }
+
+        /// Execute a statement, anything but an evaluated expression yields [`Value::Void`].
+        pub fn process(&mut self, stmt: Stmt) -> eyre::Result<Value> {
+            self.step(None, stmt)
+        }
+
+        /// Like [`Evaluator::process`], with the statement's offset into the program for the trace.
+        pub fn process_at(&mut self, offset: usize, stmt: Stmt) -> eyre::Result<Value> {
+            self.step(Some(offset), stmt)
+        }
+
+        fn step(&mut self, offset: Option<usize>, stmt: Stmt) -> eyre::Result<Value> {
+            let before = self.mode;
+            let recorded = self.trace.is_some().then(|| stmt.clone());
+            let value = match stmt {
+                Stmt::SetMode(mode) => {
+                    self.mode = mode;
+                    Value::Void
+                },
+                Stmt::Evaluate(expr) => match self.mode {
+                    Mode::Eval => expr.eval()?,
+                    Mode::Ignore => Value::Void,
+                },
+            };
+            if let (Some(trace), Some(stmt)) = (&mut self.trace, recorded) {
+                trace.steps.push(Step { offset, stmt, before, after: self.mode, value: value.clone() });
+            }
+            Ok(value)
+        }
+    }
+}
+
+pub mod diagnostics {
+    use std::{fmt, num::ParseIntError};
+    use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError};
+
+    /// Error collecting what the parsers expected at the furthest position they got to.
+    ///
+    /// Contexts only count when they start right at the failure, so `context("`(`", tag("("))`
+    /// describes itself while a context around a whole call stays out of the way.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Expected<'a> {
+        pub input: &'a str,
+        pub expected: Vec<&'static str>,
+    }
+
+    impl<'a> ParseError<&'a str> for Expected<'a> {
+        fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+            Self { input, expected: Vec::new() }
+        }
+
+        fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+            other
+        }
+
+        fn or(mut self, other: Self) -> Self {
+            match self.input.len().cmp(&other.input.len()) {
+                std::cmp::Ordering::Less => self,
+                std::cmp::Ordering::Greater => other,
+                std::cmp::Ordering::Equal => {
+                    for expected in other.expected {
+                        if !self.expected.contains(&expected) {
+                            self.expected.push(expected);
+                        }
+                    }
+                    self
+                },
+            }
+        }
+    }
+
+    impl<'a> ContextError<&'a str> for Expected<'a> {
+        fn add_context(input: &'a str, context: &'static str, mut other: Self) -> Self {
+            if other.expected.is_empty() && input.len() == other.input.len() {
+                other.expected.push(context);
+            }
+            other
+        }
+    }
+
+    impl<'a> FromExternalError<&'a str, ParseIntError> for Expected<'a> {
+        fn from_external_error(input: &'a str, _: ErrorKind, _: ParseIntError) -> Self {
+            Self { input, expected: vec!["a literal that fits into an integer"] }
+        }
+    }
+
+    /// Parse failure pointing into the source, rendered with the offending line and a caret under
+    /// the failure.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Diagnostic {
+        pub offset: usize,
+        pub expected: Vec<&'static str>,
+        line: String,
+        line_number: usize,
+        column: usize,
+    }
+
+    impl Diagnostic {
+        pub fn new(source: &str, offset: usize, expected: impl IntoIterator<Item = &'static str>) -> Self {
+            let start = source[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+            let end = source[offset..].find('\n').map_or(source.len(), |newline| offset + newline);
+            Self {
+                offset,
+                expected: expected.into_iter().collect(),
+                line: source[start..end].to_owned(),
+                line_number: source[..start].matches('\n').count() + 1,
+                column: source[start..offset].chars().count() + 1,
+            }
+        }
+
+        pub fn from_nom(source: &str, err: nom::Err<Expected<'_>>) -> Self {
+            match err {
+                nom::Err::Error(err) | nom::Err::Failure(err) => {
+                    Self::new(source, source.len() - err.input.len(), err.expected)
+                },
+                nom::Err::Incomplete(_) => Self::new(source, source.len(), ["more input"]),
+            }
+        }
+    }
+
+    impl fmt::Display for Diagnostic {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.expected.as_slice() {
+                [] => write!(f, "unexpected input")?,
+                [expected] => write!(f, "expected {expected}")?,
+                [init @ .., last] => write!(f, "expected {} or {last}", init.join(", "))?,
+            }
+            let gutter = " ".repeat(self.line_number.to_string().len());
+            writeln!(f, " at {}:{}", self.line_number, self.column)?;
+            writeln!(f, "{gutter} |")?;
+            writeln!(f, "{} | {}", self.line_number, self.line)?;
+            write!(f, "{gutter} | {:>column$}", "^", column = self.column)
+        }
+    }
+
+    impl std::error::Error for Diagnostic {}
+
+    /// Run `parser` over all of `source`, trailing whitespace aside.
+    pub fn parse_exact<'a, T>(
+        source: &'a str,
+        mut parser: impl FnMut(&'a str) -> nom::IResult<&'a str, T, Expected<'a>>,
+    ) -> Result<T, Diagnostic> {
+        match parser(source) {
+            Ok((remaining, _)) if !remaining.trim().is_empty() => {
+                Err(Diagnostic::new(source, source.len() - remaining.len(), ["end of input"]))
+            },
+            Ok((_, parsed)) => Ok(parsed),
+            Err(err) => Err(Diagnostic::from_nom(source, err)),
+        }
+    }
+}
+
+/// Every valid statement of a corrupted program in source order, garbage in between is skipped.
+///
+/// Benchmarks faster than [`tokens::parse`] (see `bench_lexer_against_nom`), so the solutions use it.
+pub fn scan(program: &str) -> impl Iterator<Item = ast::Stmt> + '_ {
+    scan_with(&ast::Grammar::PUZZLE, program)
+}
+
+pub fn scan_with<'a>(grammar: &'a ast::Grammar, program: &'a str) -> impl Iterator<Item = ast::Stmt> + 'a {
+    scan_spanned_with(grammar, program).map(|(_, stmt)| stmt)
+}
+
+/// Like [`scan_with`], with every statement's byte offset into the program.
+pub fn scan_spanned_with<'a>(grammar: &'a ast::Grammar, program: &'a str) -> impl Iterator<Item = (usize, ast::Stmt)> + 'a {
+    let mut rest = program;
+    std::iter::from_fn(move || {
+        while !rest.is_empty() {
+            if let Ok((remaining, stmt)) = ast::Stmt::parse_with::<nom::error::Error<_>>(grammar, rest) {
+                let offset = program.len() - rest.len();
+                rest = remaining;
+                return Some((offset, stmt));
+            }
+            let skip = rest.char_indices().nth(1).map_or(rest.len(), |(index, _)| index);
+            rest = rest[skip..].find(|char| grammar.may_start(char)).map_or("", |start| &rest[skip + start..]);
+        }
+        None
+    })
+}
+
+/// Evaluate statements and add up the results, the total is overflow-checked as well.
+pub fn run(stmts: impl Iterator<Item = ast::Stmt>) -> eyre::Result<ast::Value> {
+    use evaluator::TryAdd;
+
+    let mut evaluator = evaluator::Evaluator::default();
+    stmts
+        .map(|stmt| evaluator.process(stmt))
+        .try_fold(ast::Value::Int(0), |total, value| match value? {
+            ast::Value::Void => Ok(total),
+            value => total.try_add(&value),
+        })
+}
+
+/// Run a program with a tracing evaluator, for seeing which calls `do()` and `don't()` let through.
+pub fn trace(program: &str) -> eyre::Result<evaluator::Trace> {
+    let mut evaluator = evaluator::Evaluator::traced();
+    for (offset, stmt) in scan_spanned_with(&ast::Grammar::PUZZLE, program) {
+        evaluator.process_at(offset, stmt)?;
+    }
+    Ok(evaluator.into_trace().unwrap_or_default())
 }
 
+pub struct Part1;
+
+pub struct Part2;
+
 pub mod part1 {
     use super::*;
 
-    pub fn solve() {
-        let day = Day::new(3, 1);
-        let result = run(&day.input());
-        day.report(result);
+    impl core::Solution<3, 1> for super::Part1 {
+        type Result = ast::Value;
+
+        /// Part one predates `do()` and `don't()`, only multiplications count.
+        fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+            run(scan(input).filter(|stmt| matches!(stmt, ast::Stmt::Evaluate(_))))
+        }
     }
 }
 
 
 pub mod part2 {
     use super::*;
-    
-    pub fn solve() {
-        let day = Day::new(3, 2);
-        let result = run(&day.input());
-        day.report(result);
+
+    impl core::Solution<3, 2> for super::Part2 {
+        type Result = ast::Value;
+
+        fn try_solve(input: &str) -> eyre::Result<Self::Result> {
+            run(scan(input))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Solution;
+    use ast::{Expr, Stmt, Value};
+    use diagnostics::Expected;
+    use evaluator::{Evaluator, Mode};
+
+    #[test]
+    fn test_part1() {
+        let program = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+        assert_eq!(Part1::solve(program).to_string(), "161");
+    }
+
+    #[test]
+    fn test_part2() {
+        let program = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        assert_eq!(Part1::solve(program).to_string(), "161");
+        assert_eq!(Part2::solve(program).to_string(), "48");
+    }
+
+    #[test]
+    fn test_scan_skips_invalid_calls() {
+        assert_eq!(Part1::solve("mul(mul(2,3),4)mul(1234,5)mul(12,3 )mul(4*mul ( 2,4)").to_string(), "6");
+        assert_eq!(Part1::solve("123 do() 4").to_string(), "0");
+        assert_eq!(scan("żmul(2,2)ódon't()").count(), 2);
+    }
+
+    #[test]
+    fn test_evaluator_modes() {
+        let mut evaluator = Evaluator::default();
+        let mul = || "mul(2,3)".parse::<Expr>().map(Stmt::Evaluate).unwrap();
+
+        assert!(matches!(evaluator.process(mul()).unwrap(), Value::Int(6)));
+        assert!(matches!(evaluator.process("don't()".parse().unwrap()).unwrap(), Value::Void));
+        assert_eq!(evaluator.mode(), Mode::Ignore);
+        assert!(matches!(evaluator.process(mul()).unwrap(), Value::Void));
+        assert!(matches!(evaluator.process("do()".parse().unwrap()).unwrap(), Value::Void));
+        assert_eq!(evaluator.mode(), Mode::Eval);
+        assert!(matches!(evaluator.process(mul()).unwrap(), Value::Int(6)));
+    }
+
+    #[test]
+    fn test_trace() {
+        let program = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let trace = trace(program).unwrap();
+        assert_eq!(trace.steps().iter().filter(|step| step.skipped()).count(), 2);
+        assert_eq!(trace.to_string(), concat!(
+            "offset  before  after   value     stmt\n",
+            "     1  eval    eval    8         mul(2,4)\n",
+            "    20  eval    ignore  void      don't()\n",
+            "    28  ignore  ignore  skipped   mul(5,5)\n",
+            "    48  ignore  ignore  skipped   mul(11,8)\n",
+            "    59  ignore  eval    void      do()\n",
+            "    64  eval    eval    40        mul(8,5)",
+        ));
+        assert!(trace.to_json().starts_with(concat!(
+            r#"[{"offset":1,"stmt":"mul(2,4)","before":"eval","after":"eval","skipped":false,"value":8},"#,
+            r#"{"offset":20,"stmt":"don't()","before":"eval","after":"ignore","skipped":false,"value":null},"#,
+        )));
+
+        let mut evaluator = Evaluator::default();
+        evaluator.process("mul(2,3)".parse().unwrap()).unwrap();
+        assert!(evaluator.trace().is_none());
+        let mut evaluator = Evaluator::traced();
+        evaluator.process("mul(2,3)".parse().unwrap()).unwrap();
+        assert_eq!(evaluator.trace().unwrap().steps()[0].offset, None);
+    }
+
+    #[test]
+    fn test_parse_diagnostics() {
+        let error = "mul(2,x)".parse::<Expr>().unwrap_err().to_string();
+        assert_eq!(error, "expected a literal at 1:7\n  |\n1 | mul(2,x)\n  |       ^");
+
+        let error = "mul(2,3".parse::<Stmt>().unwrap_err().to_string();
+        assert!(error.starts_with("expected `)` at 1:8"), "{error}");
+        let error = "xmul(2,3)".parse::<Stmt>().unwrap_err().to_string();
+        assert!(error.starts_with("expected `do()`, `don't()` or `mul` at 1:1"), "{error}");
+        let error = "mul(2,3)x".parse::<Stmt>().unwrap_err().to_string();
+        assert!(error.starts_with("expected end of input at 1:9"), "{error}");
+
+        let nested = |program| diagnostics::parse_exact(program, |input| Expr::parse_with(&ast::Grammar::EXTENDED, input));
+        let error = nested("mul(2,add(3,x))").unwrap_err();
+        assert_eq!((error.offset, error.expected.as_slice()), (12, ["`add`", "`sub`", "`mul`", "`div`", "a literal"].as_slice()));
+        assert!(error.to_string().starts_with("expected `add`, `sub`, `mul`, `div` or a literal at 1:13"), "{error}");
+
+        let error = diagnostics::Diagnostic::new("mul(2,3)\nmul(4 5)", 14, ["`,`"]);
+        assert_eq!(error.to_string(), "expected `,` at 2:6\n  |\n2 | mul(4 5)\n  |      ^");
+    }
+
+    #[test]
+    fn test_lexer_diagnostics() {
+        assert_eq!(tokens::parse_exact("don't()").unwrap(), Stmt::SetMode(Mode::Ignore));
+        assert_eq!(tokens::parse_exact("mul(2,3)").unwrap(), "mul(2,3)".parse().unwrap());
+
+        let error = tokens::parse_exact("mul(2;3)").unwrap_err();
+        assert_eq!(error.to_string(), "expected `,` at 1:6\n  |\n1 | mul(2;3)\n  |      ^");
+        assert_eq!(tokens::parse_exact("mul(2,3").unwrap_err().expected, ["`)`"]);
+        assert_eq!(tokens::parse_exact("do()do()").unwrap_err().offset, 4);
+    }
+
+    #[test]
+    fn test_extended_grammar() {
+        let eval = |program: &str| Expr::parse_with::<Expected>(&ast::Grammar::EXTENDED, program).unwrap().1.eval();
+
+        assert_eq!(eval("mul(add(1,2),3)").unwrap(), Value::Int(9));
+        assert_eq!(eval("div(sub(10,4),add(1,2))").unwrap(), Value::Int(2));
+        assert_eq!(eval("mul(123456,2)").unwrap(), Value::Int(246912));
+        assert!(eval("div(1,sub(2,2))").unwrap_err().to_string().contains("division by zero"));
+        assert!(eval("sub(1,2)").unwrap_err().to_string().contains("overflows"));
+        assert!(eval("mul(9999999999,9999999999)").unwrap_err().to_string().contains("overflows"));
+        assert_eq!(eval("add(10000000000000000000,0)").unwrap(), Value::Int(10000000000000000000));
+        assert!(Expr::parse_with::<Expected>(&ast::Grammar::EXTENDED, "99999999999999999999").is_err());
+
+        let program = "add(1,2)mul(add(1,2),3)xdiv(8,2)";
+        assert_eq!(run(scan_with(&ast::Grammar::EXTENDED, program)).unwrap(), Value::Int(16));
+        assert_eq!(run(scan(program)).unwrap(), Value::Int(0));
+
+        let wide = ast::Grammar { literal_width: 5, ..ast::Grammar::PUZZLE };
+        assert_eq!(run(scan_with(&wide, "mul(12345,2)mul(123456,2)")).unwrap(), Value::Int(24690));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_nesting_depth() {
+        let nested = |depth: usize| format!("{}1{}", "add(1,".repeat(depth), ")".repeat(depth));
+        let parse = |program: &str| diagnostics::parse_exact(program, |input| Expr::parse_with(&ast::Grammar::EXTENDED, input));
+
+        assert_eq!(parse(&nested(64)).unwrap().eval().unwrap(), Value::Int(65));
+        let error = parse(&nested(65)).unwrap_err();
+        assert_eq!((error.offset, error.expected.as_slice()), (64 * 6, ["a literal"].as_slice()));
+        assert!(parse(&"mul(".repeat(100_000)).is_err());
+        assert_eq!(run(scan_with(&ast::Grammar::EXTENDED, &"mul(".repeat(1_000))).unwrap(), Value::Int(0));
+
+        let flat = ast::Grammar { max_depth: 0, ..ast::Grammar::EXTENDED };
+        assert!(Expr::parse_call_with::<Expected>(&flat, "add(1,2)").is_err());
+        assert_eq!(Expr::parse_with::<Expected>(&flat, "12").unwrap().1.eval().unwrap(), Value::Int(12));
+        assert_eq!(run(scan_with(&flat, "add(1,2)do()")).unwrap(), Value::Int(0));
+    }
+
+    const FRAGMENTS: [&str; 18] = [
+        "mul", "(", ")", ",", "do", "don't", "n't", "1", "23", "456", "7890",
+        " ", "x", "ż", "mul(2,3)", "do()", "don't()", "mul(12,345)",
+    ];
+
+    fn program(fragments: impl IntoIterator<Item = usize>) -> String {
+        fragments.into_iter().map(|fragment| FRAGMENTS[fragment % FRAGMENTS.len()]).collect()
+    }
+
+    #[test]
+    fn test_lexer_spans() {
+        let program = "xmul(2,4)&don't()";
+        let stmts = tokens::parse(program).collect::<Vec<_>>();
+        assert_eq!(stmts.len(), 2);
+        assert_eq!(&program[stmts[0].0.clone()], "mul(2,4)");
+        assert_eq!(&program[stmts[1].0.clone()], "don't()");
+        assert_eq!(tokens::lex("mul(1234").map(|(token, _)| token).collect::<Vec<_>>(), [
+            Ok(tokens::Token::KwMul),
+            Ok(tokens::Token::LeftParenthesis),
+            Ok(tokens::Token::Int(123)),
+            Ok(tokens::Token::Int(4)),
+        ]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_lexer_matches_nom(fragments in proptest::collection::vec(0..FRAGMENTS.len(), 0..40)) {
+            let program = program(fragments);
+            proptest::prop_assert_eq!(
+                scan(&program).collect::<Vec<_>>(),
+                tokens::parse(&program).map(|(_, stmt)| stmt).collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn bench_lexer_against_nom() {
+        let mut random = core::bench::Lcg::new(0);
+        let program = program(std::iter::repeat_with(|| random.next_u32() as usize).take(500_000));
+
+        let nom = core::bench::measure(10, || run(scan(&program)).unwrap());
+        let logos = core::bench::measure(10, || run(tokens::parse(&program).map(|(_, stmt)| stmt)).unwrap());
+        println!("day 3 over {} bytes: nom {nom:?}, logos {logos:?}", program.len());
+        assert_eq!(run(scan(&program)).unwrap(), run(tokens::parse(&program).map(|(_, stmt)| stmt)).unwrap());
+    }
+}
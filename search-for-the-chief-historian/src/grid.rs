@@ -0,0 +1,596 @@
+//! Row-major 2D grids, the shape most grid puzzles come in.
+
+use std::{fmt, ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign}, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Coords {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl From<(u16, u16)> for Coords {
+    fn from((x, y): (u16, u16)) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Position or offset on an unbounded grid, `y` grows downwards like row numbers do.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub const ORIGIN: Self = Self::new(0, 0);
+
+    pub const fn new(x: i64, y: i64) -> Self {
+        Self { x, y }
+    }
+
+    /// Taxicab distance, the number of orthogonal steps between the points.
+    pub fn manhattan(self, other: Self) -> u64 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// Chessboard distance, the number of king moves between the points.
+    pub fn chebyshev(self, other: Self) -> u64 {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+}
+
+impl From<(i64, i64)> for Point {
+    fn from((x, y): (i64, i64)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Coords> for Point {
+    fn from(Coords { x, y }: Coords) -> Self {
+        Self { x: x.into(), y: y.into() }
+    }
+}
+
+impl TryFrom<Point> for Coords {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(Point { x, y }: Point) -> Result<Self, Self::Error> {
+        Ok(Self { x: x.try_into()?, y: y.try_into()? })
+    }
+}
+
+impl Add for Point {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub for Point {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Mul<i64> for Point {
+    type Output = Self;
+
+    fn mul(self, factor: i64) -> Self::Output {
+        Self { x: self.x * factor, y: self.y * factor }
+    }
+}
+
+impl Neg for Point {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self { x: -self.x, y: -self.y }
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Point {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+/// Compass direction on the grid, up is towards the first row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction {
+    /// The four orthogonal directions, clockwise from up.
+    pub const ORTHOGONAL: [Self; 4] = [Self::Up, Self::Right, Self::Down, Self::Left];
+
+    /// The four diagonal directions, clockwise from up right.
+    pub const DIAGONAL: [Self; 4] = [Self::UpRight, Self::DownRight, Self::DownLeft, Self::UpLeft];
+
+    /// All eight directions, clockwise from up.
+    pub const ALL: [Self; 8] = [
+        Self::Up, Self::UpRight, Self::Right, Self::DownRight,
+        Self::Down, Self::DownLeft, Self::Left, Self::UpLeft,
+    ];
+
+    /// Unit step in this direction, diagonal steps move along both axes.
+    pub const fn offset(self) -> Point {
+        match self {
+            Self::Up => Point::new(0, -1),
+            Self::UpRight => Point::new(1, -1),
+            Self::Right => Point::new(1, 0),
+            Self::DownRight => Point::new(1, 1),
+            Self::Down => Point::new(0, 1),
+            Self::DownLeft => Point::new(-1, 1),
+            Self::Left => Point::new(-1, 0),
+            Self::UpLeft => Point::new(-1, -1),
+        }
+    }
+
+    pub const fn is_diagonal(self) -> bool {
+        matches!(self, Self::UpRight | Self::DownRight | Self::DownLeft | Self::UpLeft)
+    }
+
+    /// Rotate by `eighths` of a full turn clockwise.
+    const fn rotate(self, eighths: usize) -> Self {
+        Self::ALL[(self as usize + eighths) % 8]
+    }
+
+    /// A quarter turn counterclockwise.
+    pub const fn turn_left(self) -> Self {
+        self.rotate(6)
+    }
+
+    /// A quarter turn clockwise.
+    pub const fn turn_right(self) -> Self {
+        self.rotate(2)
+    }
+
+    pub const fn opposite(self) -> Self {
+        self.rotate(4)
+    }
+}
+
+impl Add<Direction> for Point {
+    type Output = Self;
+
+    fn add(self, direction: Direction) -> Self::Output {
+        self + direction.offset()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vec2d<T> {
+    width: usize,
+    height: usize,
+    data: Vec<T>,
+}
+
+impl<T> Vec2d<T> {
+    /// Most cells along either side of a grid, every cell has to be addressable by [`Coords`].
+    pub const MAX_SIDE: usize = u16::MAX as usize + 1;
+
+    pub fn new_from_default(width: usize, height: usize) -> Self
+    where
+        T: Default + Clone
+    {
+        assert!(width <= Self::MAX_SIDE && height <= Self::MAX_SIDE, "{width}x{height} grid is too large for `Coords`");
+        Self {
+            width,
+            height,
+            data: vec![T::default(); width * height],
+        }
+    }
+
+    pub fn from_slice(data: &[T], stride: usize) -> Self
+    where
+        T: Clone
+    {
+        Self::from_vec(Vec::from(data), stride)
+    }
+
+    /// Grid of `stride` wide rows taken from `data`, whose length has to be a multiple of it.
+    pub fn from_vec(data: Vec<T>, stride: usize) -> Self {
+        assert!(stride > 0 && data.len().is_multiple_of(stride) || data.is_empty());
+        let height = data.len().checked_div(stride).unwrap_or(0);
+        assert!(stride <= Self::MAX_SIDE && height <= Self::MAX_SIDE, "{stride}x{height} grid is too large for `Coords`");
+        Self {
+            width: stride,
+            height,
+            data,
+        }
+    }
+
+    /// Grid from rows of cells, failing on rows of a different length than the first one and on
+    /// grids with more than [`Vec2d::MAX_SIDE`] rows or columns.
+    ///
+    /// Rows are counted from one in the error, the way editors number lines.
+    pub fn from_rows<R: IntoIterator<Item = T>>(rows: impl IntoIterator<Item = R>) -> eyre::Result<Self> {
+        let mut width = None;
+        let mut data = Vec::new();
+        for (index, row) in rows.into_iter().enumerate() {
+            let before = data.len();
+            data.extend(row);
+            let length = data.len() - before;
+            eyre::ensure!(index < Self::MAX_SIDE, "line {}: more than {} lines", index + 1, Self::MAX_SIDE);
+            eyre::ensure!(length <= Self::MAX_SIDE, "line {}: {} cells, more than {}", index + 1, length, Self::MAX_SIDE);
+            let expected = *width.get_or_insert(length);
+            eyre::ensure!(
+                length == expected,
+                "line {}: expected {} cells like the first line, found {}", index + 1, expected, length,
+            );
+        }
+        Ok(Self::from_vec(data, width.unwrap_or(0)))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, Coords { x, y }: Coords) -> bool {
+        (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    pub fn get(&self, coords: Coords) -> Option<&T> {
+        self.contains(coords).then(|| &self[coords])
+    }
+
+    pub fn get_mut(&mut self, coords: Coords) -> Option<&mut T> {
+        self.contains(coords).then(|| &mut self[coords])
+    }
+
+    /// Cells in row-major order.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    /// Every coordinate of the grid in row-major order.
+    pub fn coords(&self) -> impl Iterator<Item = Coords> {
+        Self::coords_of(self.width, self.height)
+    }
+
+    fn coords_of(width: usize, height: usize) -> impl Iterator<Item = Coords> {
+        let coord = |value: usize| u16::try_from(value).expect("grid sides are at most `MAX_SIDE`");
+        (0..height).flat_map(move |y| (0..width).map(move |x| Coords { x: coord(x), y: coord(y) }))
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        // `chunks_exact` panics on a zero width, which only an empty grid has
+        self.data.chunks_exact(self.width.max(1))
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        assert!(x < self.width);
+        self.data.iter().skip(x).step_by(self.width)
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+        (0..self.width).map(|x| self.column(x))
+    }
+
+    /// Coordinates of `point` if it lies inside the grid.
+    pub fn checked(&self, point: Point) -> Option<Coords> {
+        Coords::try_from(point).ok().filter(|&coords| self.contains(coords))
+    }
+
+    /// Coordinates of `point` on a grid repeating itself in every direction, `None` on an empty grid.
+    pub fn wrap(&self, Point { x, y }: Point) -> Option<Coords> {
+        (self.width > 0 && self.height > 0).then(|| Coords {
+            x: x.rem_euclid(self.width as i64) as u16,
+            y: y.rem_euclid(self.height as i64) as u16,
+        })
+    }
+
+    /// Neighbor of `coords` in `direction`, if there is one inside the grid.
+    pub fn step(&self, coords: Coords, direction: Direction) -> Option<Coords> {
+        self.checked(Point::from(coords) + direction)
+    }
+
+    /// Orthogonal neighbors inside the grid, clockwise from up.
+    pub fn neighbors4(&self, coords: Coords) -> impl Iterator<Item = Coords> + '_ {
+        Direction::ORTHOGONAL.into_iter().filter_map(move |direction| self.step(coords, direction))
+    }
+
+    /// Orthogonal and diagonal neighbors inside the grid, clockwise from up.
+    pub fn neighbors8(&self, coords: Coords) -> impl Iterator<Item = Coords> + '_ {
+        Direction::ALL.into_iter().filter_map(move |direction| self.step(coords, direction))
+    }
+
+    /// Grid built cell by cell from the coordinates of a `width` by `height` grid.
+    fn from_fn(width: usize, height: usize, mut cell: impl FnMut(Coords) -> T) -> Self {
+        let data = Self::coords_of(width, height).map(&mut cell).collect();
+        Self { width, height, data }
+    }
+
+    /// Rows become columns.
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone
+    {
+        Self::from_fn(self.height, self.width, |Coords { x, y }| self[Coords { x: y, y: x }].clone())
+    }
+
+    /// Rotate a quarter turn clockwise, the first row becomes the last column.
+    pub fn rotate_clockwise(&self) -> Self
+    where
+        T: Clone
+    {
+        let height = self.height as u16;
+        Self::from_fn(self.height, self.width, |Coords { x, y }| self[Coords { x: y, y: height - 1 - x }].clone())
+    }
+
+    /// Rotate a quarter turn counterclockwise, the first row becomes the first column.
+    pub fn rotate_counterclockwise(&self) -> Self
+    where
+        T: Clone
+    {
+        let width = self.width as u16;
+        Self::from_fn(self.height, self.width, |Coords { x, y }| self[Coords { x: width - 1 - y, y: x }].clone())
+    }
+
+    /// Borrowed `width` by `height` window with its top left corner at `origin`.
+    ///
+    /// Panics if the window does not fit into the grid.
+    pub fn view(&self, origin: Coords, width: usize, height: usize) -> View<'_, T> {
+        assert!(
+            origin.x as usize + width <= self.width && origin.y as usize + height <= self.height,
+            "view out of bounds",
+        );
+        View { grid: self, origin, width, height }
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> Vec2d<U> {
+        Vec2d {
+            width: self.width,
+            height: self.height,
+            data: self.data.iter().map(&mut f).collect(),
+        }
+    }
+}
+
+impl<T> std::ops::Index<Coords> for Vec2d<T> {
+    type Output = T;
+
+    fn index(&self, Coords { x, y }: Coords) -> &Self::Output {
+        &self.data[x as usize + y as usize * self.width]
+    }
+}
+
+impl<T> std::ops::IndexMut<Coords> for Vec2d<T> {
+    fn index_mut(&mut self, Coords { x, y }: Coords) -> &mut Self::Output {
+        &mut self.data[x as usize + y as usize * self.width]
+    }
+}
+
+/// Rows on separate lines, cells written back to back.
+impl<T: fmt::Display> fmt::Display for Vec2d<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            row.iter().try_for_each(|cell| cell.fmt(f))?;
+        }
+        Ok(())
+    }
+}
+
+/// Grid of characters, one row per line.
+impl FromStr for Vec2d<char> {
+    type Err = eyre::Report;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Self::from_rows(input.lines().map(str::chars))
+    }
+}
+
+/// Window into a [`Vec2d`], coordinates are relative to its top left corner.
+#[derive(Debug, Clone, Copy)]
+pub struct View<'a, T> {
+    grid: &'a Vec2d<T>,
+    origin: Coords,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, T> View<'a, T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, Coords { x, y }: Coords) -> Option<&'a T> {
+        ((x as usize) < self.width && (y as usize) < self.height)
+            .then(|| &self.grid[Coords { x: self.origin.x + x, y: self.origin.y + y }])
+    }
+
+    pub fn row(&self, y: usize) -> &'a [T] {
+        assert!(y < self.height);
+        let start = self.origin.x as usize;
+        &self.grid.row(self.origin.y as usize + y)[start..start + self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        (0..self.height).map(|y| self.row(y))
+    }
+
+    pub fn to_vec2d(&self) -> Vec2d<T>
+    where
+        T: Clone
+    {
+        Vec2d::from_vec(self.rows().flatten().cloned().collect(), self.width)
+    }
+}
+
+impl<T> std::ops::Index<Coords> for View<'_, T> {
+    type Output = T;
+
+    fn index(&self, coords: Coords) -> &Self::Output {
+        self.get(coords).expect("coordinates outside of view")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> Vec2d<char> {
+        "abc\ndef".parse().unwrap()
+    }
+
+    #[test]
+    fn test_from_str_and_display() {
+        let grid = grid();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[Coords { x: 2, y: 1 }], 'f');
+        assert_eq!(grid.to_string(), "abc\ndef");
+
+        let error = "abc\nde\nfgh".parse::<Vec2d<char>>().unwrap_err();
+        assert_eq!(error.to_string(), "line 2: expected 3 cells like the first line, found 2");
+        assert_eq!("".parse::<Vec2d<char>>().unwrap().coords().count(), 0);
+    }
+
+    #[test]
+    fn test_get() {
+        let mut grid = grid();
+        assert_eq!(grid.get((1, 1).into()), Some(&'e'));
+        assert_eq!(grid.get((3, 0).into()), None);
+        assert_eq!(grid.get((0, 2).into()), None);
+        *grid.get_mut((0, 0).into()).unwrap() = 'z';
+        assert_eq!(grid.row(0), ['z', 'b', 'c']);
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let grid = grid();
+        assert_eq!(grid.rows().collect::<Vec<_>>(), [['a', 'b', 'c'], ['d', 'e', 'f']]);
+        assert_eq!(grid.column(1).collect::<String>(), "be");
+        assert_eq!(grid.columns().map(|column| column.collect::<String>()).collect::<Vec<_>>(), ["ad", "be", "cf"]);
+        assert_eq!(grid.coords().map(|coords| grid[coords]).collect::<String>(), "abcdef");
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let grid = "abc\ndef\nghi".parse::<Vec2d<char>>().unwrap();
+        let neighbors4 = |coords: (u16, u16)| grid.neighbors4(coords.into()).map(|coords| grid[coords]).collect::<String>();
+        let neighbors8 = |coords: (u16, u16)| grid.neighbors8(coords.into()).map(|coords| grid[coords]).collect::<String>();
+        assert_eq!(neighbors4((1, 1)), "bfhd");
+        assert_eq!(neighbors4((0, 0)), "bd");
+        assert_eq!(neighbors8((1, 1)), "bcfihgda");
+        assert_eq!(neighbors8((2, 2)), "fhe");
+    }
+
+    #[test]
+    fn test_point_arithmetic() {
+        let (a, b) = (Point::new(1, -2), Point::new(-3, 4));
+        assert_eq!(a + b, Point::new(-2, 2));
+        assert_eq!(a - b, Point::new(4, -6));
+        assert_eq!(b * 3, Point::new(-9, 12));
+        assert_eq!(-a, Point::new(-1, 2));
+        assert_eq!(a.manhattan(b), 10);
+        assert_eq!(a.chebyshev(b), 6);
+        assert_eq!(Point::ORIGIN + Direction::UpLeft + Direction::Down, Point::new(-1, 0));
+
+        assert_eq!(Coords::try_from(Point::new(2, 3)), Ok(Coords { x: 2, y: 3 }));
+        assert!(Coords::try_from(Point::new(-1, 0)).is_err());
+        assert!(Coords::try_from(Point::new(0, 1 << 16)).is_err());
+    }
+
+    #[test]
+    fn test_direction() {
+        for direction in Direction::ALL {
+            assert_eq!(direction.turn_left().turn_right(), direction);
+            assert_eq!(direction.turn_right().turn_right(), direction.opposite());
+            assert_eq!(direction.opposite().offset(), -direction.offset());
+            assert_eq!(direction.offset().chebyshev(Point::ORIGIN), 1);
+            assert_eq!(direction.turn_left().is_diagonal(), direction.is_diagonal());
+        }
+        assert_eq!(Direction::Up.turn_right(), Direction::Right);
+        assert_eq!(Direction::Up.turn_left(), Direction::Left);
+        assert_eq!(Direction::UpRight.turn_right(), Direction::DownRight);
+        assert_eq!(Direction::DownLeft.opposite(), Direction::UpRight);
+    }
+
+    #[test]
+    fn test_oversized_grid() {
+        let side = Vec2d::<char>::MAX_SIDE;
+        assert!("A".repeat(side).parse::<Vec2d<char>>().is_ok());
+        let error = "A\n".repeat(side + 1).parse::<Vec2d<char>>().unwrap_err();
+        assert_eq!(error.to_string(), format!("line {}: more than {side} lines", side + 1));
+    }
+
+    #[test]
+    fn test_out_of_grid_positions() {
+        let grid = grid();
+        assert_eq!(grid.checked(Point::new(2, 1)), Some(Coords { x: 2, y: 1 }));
+        assert_eq!(grid.checked(Point::new(3, 1)), None);
+        assert_eq!(grid.checked(Point::new(-1, 1)), None);
+        assert_eq!(grid.wrap(Point::new(-1, 5)).map(|coords| grid[coords]), Some('f'));
+        assert_eq!(grid.wrap(Point::new(7, -4)).map(|coords| grid[coords]), Some('b'));
+        assert_eq!("".parse::<Vec2d<char>>().unwrap().wrap(Point::new(1, 1)), None);
+        assert_eq!(grid.step((0, 0).into(), Direction::DownRight), Some(Coords { x: 1, y: 1 }));
+        assert_eq!(grid.step((0, 0).into(), Direction::UpRight), None);
+    }
+
+    #[test]
+    fn test_transpose_and_rotation() {
+        let grid = grid();
+        assert_eq!(grid.transpose().to_string(), "ad\nbe\ncf");
+        assert_eq!(grid.rotate_clockwise().to_string(), "da\neb\nfc");
+        assert_eq!(grid.rotate_counterclockwise().to_string(), "cf\nbe\nad");
+        assert_eq!(grid.rotate_clockwise().rotate_counterclockwise(), grid);
+        assert_eq!(grid.rotate_clockwise().rotate_clockwise().to_string(), "fed\ncba");
+        assert_eq!(grid.transpose().transpose(), grid);
+    }
+
+    #[test]
+    fn test_view() {
+        let grid = "abcd\nefgh\nijkl".parse::<Vec2d<char>>().unwrap();
+        let view = grid.view((1, 1).into(), 2, 2);
+        assert_eq!(view[Coords { x: 0, y: 0 }], 'f');
+        assert_eq!(view.get((2, 0).into()), None);
+        assert_eq!(view.rows().collect::<Vec<_>>(), [['f', 'g'], ['j', 'k']]);
+        assert_eq!(view.to_vec2d().to_string(), "fg\njk");
+    }
+
+    #[test]
+    #[should_panic(expected = "view out of bounds")]
+    fn test_view_out_of_bounds() {
+        grid().view((2, 0).into(), 2, 1);
+    }
+
+    #[test]
+    fn test_map() {
+        let grid = "123\n456".parse::<Vec2d<char>>().unwrap();
+        let digits = grid.map(|cell| cell.to_digit(10).unwrap());
+        assert_eq!(digits.iter().sum::<u32>(), 21);
+        assert_eq!(digits.map(|digit| digit * 2).to_string(), "246\n81012");
+    }
+}
@@ -0,0 +1,8 @@
+pub mod combinators;
+pub mod core;
+pub mod grid;
+pub mod puzzle;
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day12;
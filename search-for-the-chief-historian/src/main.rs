@@ -1,8 +1,9 @@
-mod core;
-mod day1;
-mod day2;
-// mod day3;
-mod day12;
+
+use search_for_the_chief_historian::{core, day1, day2, day3, day12};
+
+use std::time::{Duration, Instant};
+
+const PARALLEL_SWITCH: &str = "--parallel";
 
 pub struct Day {
     number: usize,
@@ -17,8 +18,10 @@ impl Day {
         }
     }
 
-    pub fn input(&self) -> eyre::Result<String> {
-        std::fs::read_to_string(format!("inputs/input-{}-{}.txt", self.number, self.part))
+    /// Input of this part, falling back to the input shared by both parts of the day, fetched
+    /// from the puzzle server if neither is cached.
+    pub fn input(&self, inputs: &core::InputManager) -> eyre::Result<String> {
+        inputs.input(self.number, self.part)
     }
 
     pub fn report<T>(&self, data: T) where T: std::fmt::Display {
@@ -26,12 +29,97 @@ impl Day {
     }
 }
 
-fn main() {
-    day1::part1::solve();
-    day1::part2::solve();
-    day2::part1::solve();
-    day2::part2::solve();
-    // day3::part1::solve();
-    // day3::part2::solve();
-    day12::part1::solve();
+/// A registered solution, type-erased so that all days fit in one table.
+#[derive(Clone, Copy)]
+struct Task {
+    day: usize,
+    part: usize,
+    solve: fn(&str) -> eyre::Result<String>,
+}
+
+struct Outcome {
+    day: Day,
+    result: eyre::Result<String>,
+    elapsed: Duration,
+}
+
+impl Task {
+    fn new<const DAY: usize, const PART: usize, S>() -> Self
+    where
+        S: core::Solution<DAY, PART>,
+        S::Result: std::fmt::Display,
+    {
+        Self {
+            day: DAY,
+            part: PART,
+            solve: |input| S::try_solve(input).map(|result| result.to_string()),
+        }
+    }
+
+    fn run(self, inputs: &core::InputManager) -> Outcome {
+        let day = Day::new(self.day, self.part);
+        let start = Instant::now();
+        let result = day.input(inputs).and_then(|input| (self.solve)(&input));
+        Outcome { day, result, elapsed: start.elapsed() }
+    }
+}
+
+impl Outcome {
+    /// Report the result or failure, and tell whether the task succeeded.
+    fn report(&self) -> bool {
+        match &self.result {
+            Ok(result) => {
+                self.day.report(format_args!("{result} ({:?})", self.elapsed));
+                true
+            },
+            Err(err) => {
+                eprintln!("day {}-{} failed: {:#} ({:?})", self.day.number, self.day.part, err, self.elapsed);
+                false
+            },
+        }
+    }
+}
+
+/// Run every task on its own thread, outcomes are returned in registration order.
+fn run_parallel(tasks: &[Task], inputs: &core::InputManager) -> Vec<Outcome> {
+    std::thread::scope(|scope| {
+        tasks.iter()
+            .map(|&task| (task, scope.spawn(move || task.run(inputs))))
+            // spawn everything before joining anything
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(task, handle)| handle.join().unwrap_or_else(|_| Outcome {
+                day: Day::new(task.day, task.part),
+                result: Err(eyre::eyre!("solution panicked")),
+                elapsed: Duration::ZERO,
+            }))
+            .collect()
+    })
+}
+
+fn main() -> eyre::Result<()> {
+    let tasks = [
+        Task::new::<1, 1, day1::Part1>(),
+        Task::new::<1, 2, day1::Part2>(),
+        Task::new::<2, 1, day2::Part1>(),
+        Task::new::<2, 2, day2::Part2>(),
+        Task::new::<3, 1, day3::Part1>(),
+        Task::new::<3, 2, day3::Part2>(),
+        Task::new::<12, 1, day12::Part1>(),
+        Task::new::<12, 2, day12::Part2>(),
+    ];
+
+    let inputs = core::InputManager::new();
+    let start = Instant::now();
+    let outcomes = if std::env::args().any(|arg| arg == PARALLEL_SWITCH) {
+        run_parallel(&tasks, &inputs)
+    } else {
+        tasks.into_iter().map(|task| task.run(&inputs)).collect()
+    };
+    let elapsed = start.elapsed();
+
+    let failed = outcomes.iter().filter(|outcome| !outcome.report()).count();
+    println!("total: {elapsed:?}");
+    eyre::ensure!(failed == 0, "{} solution(s) failed", failed);
+    Ok(())
 }
@@ -0,0 +1,279 @@
+/// Output style of [`Puzzle::render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Markdown,
+}
+
+/// Puzzle description extracted from the `<article>` elements of a day's page.
+///
+/// Part two gets its own article once part one is solved.
+#[derive(Debug)]
+pub struct Puzzle {
+    articles: Vec<String>,
+}
+
+impl Puzzle {
+    pub fn from_html(html: &str) -> Self {
+        let mut articles = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find("<article") {
+            let Some(body) = rest[start..].find('>').map(|end| &rest[start + end + 1..]) else {
+                break;
+            };
+            let end = body.find("</article>").unwrap_or(body.len());
+            articles.push(body[..end].to_owned());
+            rest = &body[end..];
+        }
+        Self { articles }
+    }
+
+    pub fn articles(&self) -> usize {
+        self.articles.len()
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let mut out = String::new();
+        for article in &self.articles {
+            render_article(article, format, &mut out);
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+
+    /// Contents of every `<pre><code>` block, in order of appearance.
+    ///
+    /// These are the worked examples, good candidates for test inputs.
+    pub fn examples(&self) -> Vec<String> {
+        let mut examples = Vec::new();
+        for article in &self.articles {
+            let mut block = None::<String>;
+            for node in Nodes(article) {
+                match (node, &mut block) {
+                    (Node::Open("pre"), _) => block = Some(String::new()),
+                    (Node::Close("pre"), Some(_)) => examples.extend(block.take()),
+                    (Node::Text(text), Some(block)) => block.push_str(&decode(text)),
+                    _ => {},
+                }
+            }
+        }
+        examples
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Node<'a> {
+    Open(&'a str),
+    Href(&'a str),
+    Close(&'a str),
+    Text(&'a str),
+}
+
+/// Minimal tokenizer good enough for the well-formed markup of puzzle pages.
+struct Nodes<'a>(&'a str);
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.0;
+        if input.is_empty() {
+            return None;
+        }
+        if !input.starts_with('<') {
+            let end = input.find('<').unwrap_or(input.len());
+            self.0 = &input[end..];
+            return Some(Node::Text(&input[..end]));
+        }
+        if let Some(comment) = input.strip_prefix("<!--") {
+            self.0 = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            return self.next();
+        }
+        let Some(end) = input.find('>') else {
+            // an unterminated tag is no markup, keep what is left as text
+            self.0 = "";
+            return Some(Node::Text(input));
+        };
+        let tag = input[1..end].trim_end_matches('/');
+        self.0 = &input[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            return Some(Node::Close(name.trim()));
+        }
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        match (name, attribute(attributes, "href")) {
+            ("a", Some(href)) => Some(Node::Href(href)),
+            _ => Some(Node::Open(name)),
+        }
+    }
+}
+
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let value = &attributes[attributes.find(&format!("{name}=\""))? + name.len() + 2..];
+    value.find('"').map(|end| &value[..end])
+}
+
+fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';')
+            .map(|end| &rest[1..end + 1])
+            .and_then(|entity| Some((entity, match entity {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => char::from_u32(u32::from_str_radix(hex, 16).ok()?)?,
+                    None => char::from_u32(entity.strip_prefix('#')?.parse().ok()?)?,
+                },
+            })));
+        match entity {
+            Some((entity, char)) => {
+                decoded.push(char);
+                rest = &rest[entity.len() + 2..];
+            },
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn render_article(html: &str, format: Format, out: &mut String) {
+    let markdown = format == Format::Markdown;
+    let mut pre = None::<String>;
+    let mut links = Vec::new();
+
+    for node in Nodes(html) {
+        if let Some(block) = &mut pre {
+            match node {
+                Node::Text(text) => block.push_str(&decode(text)),
+                Node::Close("pre") => {
+                    let block = pre.take().unwrap_or_default();
+                    if markdown {
+                        out.push_str("```\n");
+                        out.push_str(&block);
+                        if !block.ends_with('\n') {
+                            out.push('\n');
+                        }
+                        out.push_str("```\n\n");
+                    } else {
+                        for line in block.lines() {
+                            out.push_str("    ");
+                            out.push_str(line);
+                            out.push('\n');
+                        }
+                        out.push('\n');
+                    }
+                },
+                _ => {},
+            }
+            continue;
+        }
+
+        match node {
+            Node::Open("pre") => pre = Some(String::new()),
+            Node::Open("h2") if markdown => out.push_str("## "),
+            Node::Close("h2" | "p") => out.push_str("\n\n"),
+            Node::Open("li") => out.push_str("- "),
+            Node::Close("li") => out.push('\n'),
+            Node::Close("ul") => out.push('\n'),
+            Node::Open("code") | Node::Close("code") if markdown => out.push('`'),
+            Node::Open("em") | Node::Close("em") if markdown => out.push('*'),
+            Node::Href(href) => {
+                links.push(href);
+                if markdown {
+                    out.push('[');
+                }
+            },
+            Node::Close("a") => {
+                if let (true, Some(href)) = (markdown, links.pop()) {
+                    out.push_str(&format!("]({href})"));
+                }
+            },
+            Node::Text(text) => {
+                let text = decode(text).replace('\n', " ");
+                if !text.trim().is_empty() || !(out.is_empty() || out.ends_with(['\n', ' '])) {
+                    out.push_str(&text);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = concat!(
+        "<html><body><main>\n",
+        "<article class=\"day-desc\"><h2>--- Day 1: Historian Hysteria ---</h2>\n",
+        "<p>Throughout the Chief's office, the <em>lists</em> are &quot;historically significant&quot;.</p>\n",
+        "<!-- hidden -->\n",
+        "<p>For example:</p>\n",
+        "<pre><code>3   4\n4   3\n<em>2</em>   5\n</code></pre>\n",
+        "<ul><li>Pair up <code>1</code> and <code>3</code>.</li></ul>\n",
+        "<p>See <a href=\"/2024/day/1/input\">your input</a> &amp; more.</p>\n",
+        "</article>\n",
+        "<p>Answer: <code>11</code></p>\n",
+        "</main></body></html>\n",
+    );
+
+    #[test]
+    fn test_extracts_articles_only() {
+        let puzzle = Puzzle::from_html(PAGE);
+        assert_eq!(puzzle.articles(), 1);
+        assert!(!puzzle.render(Format::Text).contains("Answer"));
+    }
+
+    #[test]
+    fn test_render_text() {
+        assert_eq!(Puzzle::from_html(PAGE).render(Format::Text), concat!(
+            "--- Day 1: Historian Hysteria ---\n\n",
+            "Throughout the Chief's office, the lists are \"historically significant\".\n\n",
+            "For example:\n\n",
+            "    3   4\n    4   3\n    2   5\n\n",
+            "- Pair up 1 and 3.\n\n",
+            "See your input & more.\n",
+        ));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        assert_eq!(Puzzle::from_html(PAGE).render(Format::Markdown), concat!(
+            "## --- Day 1: Historian Hysteria ---\n\n",
+            "Throughout the Chief's office, the *lists* are \"historically significant\".\n\n",
+            "For example:\n\n",
+            "```\n3   4\n4   3\n2   5\n```\n\n",
+            "- Pair up `1` and `3`.\n\n",
+            "See [your input](/2024/day/1/input) & more.\n",
+        ));
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(Puzzle::from_html(PAGE).examples(), ["3   4\n4   3\n2   5\n"]);
+    }
+
+    #[test]
+    fn test_unterminated_tag() {
+        assert_eq!(Puzzle::from_html("<article><p>x</p><").render(Format::Text), "x\n\n<\n");
+        assert_eq!(Puzzle::from_html("<article><p>x <em").render(Format::Text), "x <em\n");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("&lt;a&gt; &#65;&#x42; &unknown; & done"), "<a> AB &unknown; & done");
+    }
+}